@@ -9,11 +9,22 @@ mod networks;
 use mcslib_common::config::ConfigLoader;
 use mcslib_common::init_log;
 use mcslib_common::once_cell::sync::Lazy;
-use mcslib_common::types::{JsonSerializable, TrackersServerConfig};
-use std::io::Result as IOResult;
+use mcslib_common::station_calibration::calibrate_base_stations;
+use mcslib_common::types::{CalibrationObservations, CameraCalibration, JsonSerializable, TrackersServerConfig};
+use mcslib_opencv::intrinsic_calibration::{calibrate_from_directory, ChessboardPattern};
+use networks::{create_data_receivers, DataReceiver};
+use std::env::args;
+use std::fs::read_to_string;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
 use std::panic::set_hook;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
 
 const APP_CONFIG_PATH: &str = "app.config";
+const CALIBRATE_BASE_STATIONS_COMMAND: &str = "calibrate-base-stations";
+const CALIBRATE_CAMERA_COMMAND: &str = "calibrate-camera";
+const STATISTICS_LOG_INTERVAL: Duration = Duration::from_secs(10);
 
 static APP_CONFIG: Lazy<TrackersServerConfig> =
     Lazy::new(|| TrackersServerConfig::load_config(APP_CONFIG_PATH).unwrap());
@@ -25,8 +36,152 @@ fn init_logging() {
     }));
 }
 
+fn log_statistics(data_receivers: &[Box<dyn DataReceiver>]) {
+    for data_receiver in data_receivers {
+        let statistics = data_receiver.statistics();
+
+        for tracker_stats in &statistics.trackers {
+            info!("{}", tracker_stats);
+        }
+
+        if statistics.dropped_frame_counter > 0 {
+            warn!(
+                "{} frame(s) dropped for {} slow subscriber(s)",
+                statistics.dropped_frame_counter, statistics.subscriber_count
+            );
+        }
+    }
+}
+
+fn usage_error(program: &str, command: &str, arguments: &str) -> IOError {
+    IOError::new(
+        IOErrorKind::InvalidInput,
+        format!("Usage: {} {} {}", program, command, arguments),
+    )
+}
+
+/// Calibrates the camera intrinsics from chessboard captures and writes a calibration file that trackers can reference
+/// through `CameraCalibrationPath`.
+fn run_camera_calibration(
+    image_directory: &str,
+    chessboard_pattern: &ChessboardPattern,
+    calibration_path: &str,
+) -> IOResult<()> {
+    let camera_calibration = calibrate_from_directory(Path::new(image_directory), chessboard_pattern)
+        .map_err(|error| IOError::new(IOErrorKind::InvalidData, error.to_string()))?;
+
+    for image_residual in &camera_calibration.image_residuals {
+        info!(
+            "Image \"{}\": RMS reprojection error {:.4}px",
+            image_residual.image_name, image_residual.rms_reprojection_error
+        );
+    }
+
+    for skipped_image in &camera_calibration.skipped_images {
+        warn!("Image \"{}\": chessboard not found, skipped", skipped_image);
+    }
+
+    for unreadable_image in &camera_calibration.unreadable_images {
+        warn!("Image \"{}\": cannot be read, skipped", unreadable_image);
+    }
+
+    info!(
+        "Camera calibration RMS reprojection error {:.4}px over {} image(s)",
+        camera_calibration.rms_reprojection_error,
+        camera_calibration.image_residuals.len()
+    );
+    CameraCalibration::save_config(&camera_calibration, calibration_path)?;
+    info!("Camera calibration written to \"{}\"", calibration_path);
+
+    Ok(())
+}
+
+/// Solves the base-station extrinsics from recorded calibration tracker observations and writes them into the app
+/// config.
+fn run_base_station_calibration(observations_path: &str, origin_station_name: &str) -> IOResult<()> {
+    let mut app_config = TrackersServerConfig::load_config(APP_CONFIG_PATH)?;
+    let observations = CalibrationObservations::from_json(&read_to_string(observations_path)?)?;
+    let calibration = calibrate_base_stations(&app_config.base_stations_config, origin_station_name, &observations.0)?;
+
+    for residual in &calibration.residuals {
+        info!(
+            "Base station \"{}\": {} observation(s), RMS residual {:.4}, max residual {:.4}",
+            residual.station_name, residual.observation_count, residual.rms_residual, residual.max_residual
+        );
+    }
+
+    app_config.base_stations_config = calibration.base_stations_config;
+    TrackersServerConfig::save_config(&app_config, APP_CONFIG_PATH)?;
+    info!("Base station extrinsics written to \"{}\"", APP_CONFIG_PATH);
+
+    Ok(())
+}
+
 fn main() -> IOResult<()> {
     init_logging();
+    let arguments: Vec<String> = args().collect();
+
+    match arguments.get(1).map(String::as_str) {
+        Some(CALIBRATE_BASE_STATIONS_COMMAND) => {
+            return match (arguments.get(2), arguments.get(3)) {
+                (Some(observations_path), Some(origin_station_name)) => {
+                    run_base_station_calibration(observations_path, origin_station_name)
+                }
+                _ => Err(usage_error(
+                    &arguments[0],
+                    CALIBRATE_BASE_STATIONS_COMMAND,
+                    "<observations.json> <origin station name>",
+                )),
+            };
+        }
+        Some(CALIBRATE_CAMERA_COMMAND) => {
+            let chessboard_pattern = match (arguments.get(3), arguments.get(4), arguments.get(5)) {
+                (Some(inner_corners_per_row), Some(inner_corners_per_column), Some(square_size)) => {
+                    match (
+                        inner_corners_per_row.parse(),
+                        inner_corners_per_column.parse(),
+                        square_size.parse(),
+                    ) {
+                        (Ok(inner_corners_per_row), Ok(inner_corners_per_column), Ok(square_size)) => {
+                            Some(ChessboardPattern {
+                                inner_corners_per_row,
+                                inner_corners_per_column,
+                                square_size,
+                            })
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+
+            return match (arguments.get(2), chessboard_pattern, arguments.get(6)) {
+                (Some(image_directory), Some(chessboard_pattern), Some(calibration_path)) => {
+                    run_camera_calibration(image_directory, &chessboard_pattern, calibration_path)
+                }
+                _ => Err(usage_error(
+                    &arguments[0],
+                    CALIBRATE_CAMERA_COMMAND,
+                    "<image directory> <inner corners per row> <inner corners per column> <square size> \
+                     <calibration.json>",
+                )),
+            };
+        }
+        _ => {}
+    }
+
     debug!("{}", APP_CONFIG.to_json());
-    Ok(())
+
+    let data_receivers = create_data_receivers(&APP_CONFIG.trackers_config);
+
+    for data_receiver in &data_receivers {
+        if let Err(error) = data_receiver.start() {
+            error!("Cannot start receiver: {}", error);
+        }
+    }
+
+    loop {
+        sleep(STATISTICS_LOG_INTERVAL);
+        log_statistics(&data_receivers);
+    }
 }
@@ -0,0 +1,233 @@
+use crossbeam_channel::{bounded, Receiver, SendTimeoutError, Sender, TrySendError};
+use mcslib_common::bytes::Bytes;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SlowSubscriberPolicy {
+    DropOldest,
+    DropNewest,
+    Block,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SubscriptionConfig {
+    pub queue_capacity: usize,
+    pub slow_subscriber_policy: SlowSubscriberPolicy,
+}
+
+/// Receiving end of one subscription. Every subscription gets its own copy of every published frame; dropping it
+/// unsubscribes.
+#[derive(Debug)]
+pub struct DataReceiverChannel {
+    receiver: Receiver<Bytes>,
+    _subscription: Arc<()>,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    sender: Sender<Bytes>,
+    receiver: Receiver<Bytes>,
+    slow_subscriber_policy: SlowSubscriberPolicy,
+    subscription: Weak<()>,
+}
+
+#[derive(Debug, Default)]
+pub struct DataBroadcaster {
+    subscribers: RwLock<Vec<Subscriber>>,
+    dropped_frame_counter: AtomicUsize,
+}
+
+impl Default for SlowSubscriberPolicy {
+    fn default() -> SlowSubscriberPolicy {
+        SlowSubscriberPolicy::DropOldest
+    }
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> SubscriptionConfig {
+        SubscriptionConfig {
+            queue_capacity: 256,
+            slow_subscriber_policy: Default::default(),
+        }
+    }
+}
+
+impl Deref for DataReceiverChannel {
+    type Target = Receiver<Bytes>;
+
+    fn deref(&self) -> &Receiver<Bytes> {
+        &self.receiver
+    }
+}
+
+impl Subscriber {
+    #[inline]
+    fn is_subscribed(&self) -> bool {
+        self.subscription.upgrade().is_some()
+    }
+
+    /// Returns `true` when the frame was delivered.
+    fn deliver(&self, frame: Bytes, is_stop_requested: &AtomicBool) -> bool {
+        match self.slow_subscriber_policy {
+            SlowSubscriberPolicy::DropNewest => self.sender.try_send(frame).is_ok(),
+            SlowSubscriberPolicy::DropOldest => {
+                let mut frame = frame;
+                let mut is_delivered = true;
+
+                loop {
+                    match self.sender.try_send(frame) {
+                        Ok(_) => return is_delivered,
+                        Err(TrySendError::Full(rejected_frame)) => {
+                            frame = rejected_frame;
+                            is_delivered &= self.receiver.try_recv().is_err();
+                        }
+                        Err(TrySendError::Disconnected(_)) => return false,
+                    }
+                }
+            }
+            SlowSubscriberPolicy::Block => {
+                let mut frame = frame;
+
+                loop {
+                    match self.sender.send_timeout(frame, BLOCK_POLL_INTERVAL) {
+                        Ok(_) => return true,
+                        Err(SendTimeoutError::Timeout(rejected_frame)) => {
+                            if is_stop_requested.load(Ordering::SeqCst) || !self.is_subscribed() {
+                                return false;
+                            }
+
+                            frame = rejected_frame;
+                        }
+                        Err(SendTimeoutError::Disconnected(_)) => return false,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl DataBroadcaster {
+    pub fn new() -> DataBroadcaster {
+        Default::default()
+    }
+
+    pub fn subscribe(&self, subscription_config: SubscriptionConfig) -> DataReceiverChannel {
+        let (sender, receiver) = bounded(subscription_config.queue_capacity.max(1));
+        let subscription = Arc::new(());
+        self.subscribers.write().unwrap().push(Subscriber {
+            sender,
+            receiver: receiver.clone(),
+            slow_subscriber_policy: subscription_config.slow_subscriber_policy,
+            subscription: Arc::downgrade(&subscription),
+        });
+
+        DataReceiverChannel {
+            receiver,
+            _subscription: subscription,
+        }
+    }
+
+    pub fn publish(&self, frame: Bytes, is_stop_requested: &AtomicBool) {
+        let mut has_unsubscribed = false;
+
+        for subscriber in self.subscribers.read().unwrap().iter() {
+            if !subscriber.is_subscribed() {
+                has_unsubscribed = true;
+                continue;
+            }
+
+            if !subscriber.deliver(frame.clone(), is_stop_requested) {
+                self.dropped_frame_counter.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        if has_unsubscribed {
+            self.subscribers
+                .write()
+                .unwrap()
+                .retain(|subscriber| subscriber.is_subscribed());
+        }
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|subscriber| subscriber.is_subscribed())
+            .count()
+    }
+
+    pub fn dropped_frame_counter(&self) -> usize {
+        self.dropped_frame_counter.load(Ordering::SeqCst)
+    }
+
+    /// Drops every subscription, so subscribers see their channel disconnect once it is drained.
+    pub fn close(&self) {
+        self.subscribers.write().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription_config(queue_capacity: usize, slow_subscriber_policy: SlowSubscriberPolicy) -> SubscriptionConfig {
+        SubscriptionConfig {
+            queue_capacity,
+            slow_subscriber_policy,
+        }
+    }
+
+    #[test]
+    fn every_subscriber_gets_every_frame() {
+        let is_stop_requested = AtomicBool::new(false);
+        let broadcaster = DataBroadcaster::new();
+        let first_channel = broadcaster.subscribe(Default::default());
+        let second_channel = broadcaster.subscribe(Default::default());
+
+        for frame in &[b"a", b"b", b"c"] {
+            broadcaster.publish(Bytes::from_static(*frame), &is_stop_requested);
+        }
+
+        for channel in &[first_channel, second_channel] {
+            let frames: Vec<Bytes> = channel.try_iter().collect();
+            assert_eq!(frames, vec![&b"a"[..], &b"b"[..], &b"c"[..]]);
+        }
+
+        assert_eq!(broadcaster.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn slow_subscriber_policies() {
+        let is_stop_requested = AtomicBool::new(false);
+        let broadcaster = DataBroadcaster::new();
+        let drop_oldest_channel = broadcaster.subscribe(subscription_config(2, SlowSubscriberPolicy::DropOldest));
+        let drop_newest_channel = broadcaster.subscribe(subscription_config(2, SlowSubscriberPolicy::DropNewest));
+
+        for frame in &[b"a", b"b", b"c"] {
+            broadcaster.publish(Bytes::from_static(*frame), &is_stop_requested);
+        }
+
+        assert_eq!(
+            drop_oldest_channel.try_iter().collect::<Vec<_>>(),
+            vec![&b"b"[..], &b"c"[..]]
+        );
+        assert_eq!(
+            drop_newest_channel.try_iter().collect::<Vec<_>>(),
+            vec![&b"a"[..], &b"b"[..]]
+        );
+        assert_eq!(broadcaster.dropped_frame_counter(), 2);
+
+        let block_channel = broadcaster.subscribe(subscription_config(1, SlowSubscriberPolicy::Block));
+        broadcaster.publish(Bytes::from_static(b"d"), &is_stop_requested);
+        is_stop_requested.store(true, Ordering::SeqCst);
+        broadcaster.publish(Bytes::from_static(b"e"), &is_stop_requested);
+        assert_eq!(block_channel.try_iter().collect::<Vec<_>>(), vec![&b"d"[..]]);
+    }
+}
@@ -1,14 +1,26 @@
-use crossbeam_channel::{Receiver, Sender};
-use crossbeam_utils::thread::ScopedJoinHandle;
+use super::{
+    DataBroadcaster, DataReceiverChannel, DataReceiverSerial, DataReceiverTCP, DataReceiverUDP, ReceiverStats,
+    SubscriptionConfig, TrackerStats,
+};
 use mcslib_common::bytes::Bytes;
-use mcslib_common::types::{SerialPortSettings as SPSettings, TrackersConfig};
-use std::collections::HashMap;
+use mcslib_common::get_timestamp_nanos;
+use mcslib_common::types::{TrackerCommunication, TrackerEndpoint, TrackersConfig};
+use mcslib_protocol::decode as decode_tracker_packet;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FormatterResult};
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
 use std::mem;
-use std::net::SocketAddrV4;
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU8, AtomicUsize, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, Builder as ThreadBuilder, JoinHandle};
+use std::time::Duration;
 
-pub type DataReceiverChannel = Receiver<Bytes>;
+pub type DataReceiverWorker = Box<dyn FnOnce(DataReceiverContext) + Send>;
+
+pub const RECONNECT_MIN_BACKOFF: Duration = Duration::from_millis(100);
+pub const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 #[repr(u8)]
 #[derive(Debug, Copy, Clone)]
@@ -20,39 +32,56 @@ pub enum DataReceiverState {
 }
 
 #[derive(Debug)]
-pub struct DataReceiverUDP<'a> {
-    state: AtomicU8,
-    is_stop_requested: AtomicBool,
-    subscriber_count: AtomicUsize,
-    receiving_counter: AtomicUsize,
-    start_timestamp: AtomicI64,
-    broadcaster_addresses: HashMap<String, TrackersConfig>,
-    pub_thread: Option<Vec<ScopedJoinHandle<'a, ()>>>,
-    pubsub_channel: Mutex<Option<(Sender<Bytes>, DataReceiverChannel)>>,
+pub enum DataReceiverError {
+    InvalidStateTransition {
+        from: DataReceiverState,
+        to: DataReceiverState,
+    },
+    IOError(String, IOError),
 }
 
-#[derive(Debug)]
-pub struct DataReceiverTCP<'a> {
-    state: AtomicU8,
-    is_stop_requested: AtomicBool,
-    subscriber_count: AtomicUsize,
-    receiving_counter: AtomicUsize,
-    start_timestamp: AtomicI64,
-    broadcaster_addresses: HashMap<String, SocketAddrV4>,
-    pub_thread: Option<Vec<ScopedJoinHandle<'a, ()>>>,
-    pubsub_channel: Mutex<Option<(Sender<Bytes>, DataReceiverChannel)>>,
+#[derive(Debug, Clone)]
+pub struct DataReceiverStatistics {
+    pub state: DataReceiverState,
+    pub subscriber_count: usize,
+    pub receiving_counter: usize,
+    pub dropped_frame_counter: usize,
+    pub start_timestamp: i64,
+    pub trackers: Vec<ReceiverStats>,
 }
 
+pub trait DataReceiver: Send + Sync {
+    fn start(&self) -> Result<(), DataReceiverError>;
+    fn stop(&self) -> Result<(), DataReceiverError>;
+    fn state(&self) -> DataReceiverState;
+    fn subscribe_with(&self, subscription_config: SubscriptionConfig) -> DataReceiverChannel;
+    fn statistics(&self) -> DataReceiverStatistics;
+
+    fn subscribe(&self) -> DataReceiverChannel {
+        self.subscribe_with(Default::default())
+    }
+}
+
+/// Handle given to every receiver worker thread to publish frames, record statistics and observe stop requests.
+#[derive(Debug, Clone)]
+pub struct DataReceiverContext {
+    is_stop_requested: Arc<AtomicBool>,
+    receiving_counter: Arc<AtomicUsize>,
+    broadcaster: Arc<DataBroadcaster>,
+    tracker_stats: Arc<TrackerStats>,
+}
+
+/// State and thread bookkeeping shared by every transport.
 #[derive(Debug)]
-pub struct DataReceiverSerial<'a> {
+pub struct DataReceiverCore {
+    transport_name: &'static str,
     state: AtomicU8,
-    is_stop_requested: AtomicBool,
-    subscriber_count: AtomicUsize,
-    receiving_counter: AtomicUsize,
+    is_stop_requested: Arc<AtomicBool>,
+    receiving_counter: Arc<AtomicUsize>,
     start_timestamp: AtomicI64,
-    broadcaster_addresses: HashMap<String, SPSettings>,
-    pub_thread: Option<Vec<ScopedJoinHandle<'a, ()>>>,
-    pubsub_channel: Mutex<Option<(Sender<Bytes>, DataReceiverChannel)>>,
+    pub_thread: Mutex<Option<Vec<JoinHandle<()>>>>,
+    broadcaster: Arc<DataBroadcaster>,
+    tracker_stats: Mutex<BTreeMap<String, Arc<TrackerStats>>>,
 }
 
 impl Into<u8> for DataReceiverState {
@@ -84,7 +113,247 @@ impl PartialEq for DataReceiverState {
 
 impl Eq for DataReceiverState {}
 
-#[allow(dead_code)]
+impl Display for DataReceiverError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            DataReceiverError::InvalidStateTransition { from, to } => {
+                write!(formatter, "Invalid state transition from {:?} to {:?}", from, to)
+            }
+            DataReceiverError::IOError(context, error) => write!(formatter, "{}: {}", context, error),
+        }
+    }
+}
+
+impl Error for DataReceiverError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DataReceiverError::IOError(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl DataReceiverContext {
+    #[inline]
+    pub fn is_stop_requested(&self) -> bool {
+        self.is_stop_requested.load(Ordering::SeqCst)
+    }
+
+    /// Publishes `frame` to every subscriber once it decodes as a tracker packet, otherwise drops it.
+    pub fn publish(&self, frame: Bytes) {
+        match decode_tracker_packet(&frame) {
+            Ok(tracker_packet) => {
+                increment_atomic_usize(&self.receiving_counter);
+                self.tracker_stats
+                    .record_packet(frame.len(), tracker_packet.header.sequence_number);
+                self.broadcaster.publish(frame, &self.is_stop_requested);
+            }
+            Err(error) => {
+                debug!(
+                    "Dropping frame from \"{}\": {}",
+                    self.tracker_stats.tracker_name(),
+                    error
+                );
+                self.tracker_stats.record_decode_error();
+            }
+        }
+    }
+
+    #[inline]
+    pub fn record_decode_error(&self) {
+        self.tracker_stats.record_decode_error();
+    }
+
+    #[inline]
+    pub fn record_receive_error(&self) {
+        self.tracker_stats.record_receive_error();
+    }
+
+    #[inline]
+    pub fn record_reconnect(&self) {
+        self.tracker_stats.record_reconnect();
+    }
+
+    pub fn sleep_unless_stopped(&self, duration: Duration) {
+        let mut remaining = duration;
+
+        while remaining > Duration::from_millis(0) && !self.is_stop_requested() {
+            let step = remaining.min(STOP_POLL_INTERVAL);
+            sleep(step);
+            remaining -= step;
+        }
+    }
+}
+
+impl DataReceiverCore {
+    pub fn new(transport_name: &'static str) -> DataReceiverCore {
+        DataReceiverCore {
+            transport_name,
+            state: AtomicU8::new(DataReceiverState::Stopped.into()),
+            is_stop_requested: Arc::new(AtomicBool::new(false)),
+            receiving_counter: Arc::new(AtomicUsize::new(0)),
+            start_timestamp: AtomicI64::new(0),
+            pub_thread: Mutex::new(None),
+            broadcaster: Arc::new(DataBroadcaster::new()),
+            tracker_stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn state(&self) -> DataReceiverState {
+        self.state.load(Ordering::SeqCst).into()
+    }
+
+    pub fn subscribe_with(&self, subscription_config: SubscriptionConfig) -> DataReceiverChannel {
+        self.broadcaster.subscribe(subscription_config)
+    }
+
+    pub fn statistics(&self) -> DataReceiverStatistics {
+        DataReceiverStatistics {
+            state: self.state(),
+            subscriber_count: self.broadcaster.subscriber_count(),
+            receiving_counter: get_atomic_usize_value(&self.receiving_counter),
+            dropped_frame_counter: self.broadcaster.dropped_frame_counter(),
+            start_timestamp: self.start_timestamp.load(Ordering::SeqCst),
+            trackers: self
+                .tracker_stats
+                .lock()
+                .unwrap()
+                .values()
+                .map(|tracker_stats| tracker_stats.snapshot())
+                .collect(),
+        }
+    }
+
+    /// Moves to `Starting`, asks `prepare_workers` for one worker per tracker, spawns them and moves to `Started`.
+    /// Any failure on the way rolls the state back to `Stopped`.
+    pub fn start<F>(&self, prepare_workers: F) -> Result<(), DataReceiverError>
+    where
+        F: FnOnce() -> Result<Vec<(String, DataReceiverWorker)>, DataReceiverError>,
+    {
+        self.transition_state(DataReceiverState::Stopped, DataReceiverState::Starting)?;
+        self.is_stop_requested.store(false, Ordering::SeqCst);
+
+        let workers = match prepare_workers() {
+            Ok(workers) => workers,
+            Err(error) => {
+                self.state.store(DataReceiverState::Stopped.into(), Ordering::SeqCst);
+                return Err(error);
+            }
+        };
+        let mut pub_threads = Vec::with_capacity(workers.len());
+        let worker_count = workers.len();
+
+        for (tracker_name, worker) in workers {
+            let context = DataReceiverContext {
+                is_stop_requested: self.is_stop_requested.clone(),
+                receiving_counter: self.receiving_counter.clone(),
+                broadcaster: self.broadcaster.clone(),
+                tracker_stats: self
+                    .tracker_stats
+                    .lock()
+                    .unwrap()
+                    .entry(tracker_name.clone())
+                    .or_insert_with(|| Arc::new(TrackerStats::new(&tracker_name)))
+                    .clone(),
+            };
+            let thread_name = format!("{}-{}", self.transport_name.to_lowercase(), tracker_name);
+            let spawn_result = ThreadBuilder::new()
+                .name(thread_name.clone())
+                .spawn(move || worker(context));
+
+            match spawn_result {
+                Ok(pub_thread) => pub_threads.push(pub_thread),
+                Err(error) => {
+                    self.is_stop_requested.store(true, Ordering::SeqCst);
+                    join_threads(pub_threads);
+                    self.state.store(DataReceiverState::Stopped.into(), Ordering::SeqCst);
+                    return Err(DataReceiverError::IOError(
+                        format!("Cannot spawn \"{}\"", thread_name),
+                        error,
+                    ));
+                }
+            }
+        }
+
+        *self.pub_thread.lock().unwrap() = Some(pub_threads);
+        self.start_timestamp.store(get_timestamp_nanos(), Ordering::SeqCst);
+        self.state.store(DataReceiverState::Started.into(), Ordering::SeqCst);
+        info!(
+            "{} receiver started for {} tracker(s)",
+            self.transport_name, worker_count
+        );
+
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), DataReceiverError> {
+        self.transition_state(DataReceiverState::Started, DataReceiverState::Stopping)?;
+        self.is_stop_requested.store(true, Ordering::SeqCst);
+
+        if let Some(pub_threads) = self.pub_thread.lock().unwrap().take() {
+            join_threads(pub_threads);
+        }
+
+        self.broadcaster.close();
+        self.state.store(DataReceiverState::Stopped.into(), Ordering::SeqCst);
+        info!(
+            "{} receiver stopped after {} frame(s)",
+            self.transport_name,
+            get_atomic_usize_value(&self.receiving_counter)
+        );
+
+        Ok(())
+    }
+
+    fn transition_state(&self, from: DataReceiverState, to: DataReceiverState) -> Result<(), DataReceiverError> {
+        self.state
+            .compare_exchange(from.into(), to.into(), Ordering::SeqCst, Ordering::SeqCst)
+            .map(|_| ())
+            .map_err(|current| DataReceiverError::InvalidStateTransition {
+                from: current.into(),
+                to,
+            })
+    }
+}
+
+impl Drop for DataReceiverCore {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+pub fn create_data_receiver(tracker_endpoint: &TrackerEndpoint) -> Box<dyn DataReceiver> {
+    let trackers_config = TrackersConfig(vec![tracker_endpoint.clone()]);
+
+    match tracker_endpoint.tracker_communication {
+        TrackerCommunication::UDP(_) => Box::new(DataReceiverUDP::new(&trackers_config)),
+        TrackerCommunication::TCP(_) => Box::new(DataReceiverTCP::new(&trackers_config)),
+        TrackerCommunication::SerialPort(_) => Box::new(DataReceiverSerial::new(&trackers_config)),
+    }
+}
+
+pub fn create_data_receivers(trackers_config: &TrackersConfig) -> Vec<Box<dyn DataReceiver>> {
+    trackers_config.0.iter().map(create_data_receiver).collect()
+}
+
+#[inline]
+pub fn is_timeout_error(error: &IOError) -> bool {
+    matches!(
+        error.kind(),
+        IOErrorKind::WouldBlock | IOErrorKind::TimedOut | IOErrorKind::Interrupted
+    )
+}
+
+fn join_threads(pub_threads: Vec<JoinHandle<()>>) {
+    for pub_thread in pub_threads {
+        let thread_name = pub_thread.thread().name().unwrap_or_default().to_string();
+
+        if pub_thread.join().is_err() {
+            error!("Receiver thread \"{}\" panicked", thread_name);
+        }
+    }
+}
+
 #[inline]
 fn increment_atomic_usize(atomic_usize: &AtomicUsize) -> usize {
     atomic_usize.fetch_add(1, Ordering::SeqCst)
@@ -96,8 +365,43 @@ fn decrement_atomic_usize(atomic_usize: &AtomicUsize) -> usize {
     atomic_usize.fetch_sub(1, Ordering::SeqCst)
 }
 
-#[allow(dead_code)]
 #[inline]
 fn get_atomic_usize_value(atomic_usize: &AtomicUsize) -> usize {
     atomic_usize.load(Ordering::SeqCst)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_receiver_state_machine() {
+        let receiver: Box<dyn DataReceiver> = Box::new(DataReceiverUDP::new(&TrackersConfig(vec![])));
+        assert_eq!(receiver.state(), DataReceiverState::Stopped);
+        assert!(receiver.stop().is_err());
+
+        receiver.start().unwrap();
+        assert_eq!(receiver.state(), DataReceiverState::Started);
+
+        match receiver.start() {
+            Err(DataReceiverError::InvalidStateTransition { from, to }) => {
+                assert_eq!(from, DataReceiverState::Started);
+                assert_eq!(to, DataReceiverState::Starting);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        receiver.stop().unwrap();
+        assert_eq!(receiver.state(), DataReceiverState::Stopped);
+    }
+
+    #[test]
+    fn create_data_receivers_per_endpoint() {
+        let receivers = create_data_receivers(&TrackersConfig::default());
+        assert_eq!(receivers.len(), TrackersConfig::default().0.len());
+
+        for receiver in receivers {
+            assert_eq!(receiver.statistics().state, DataReceiverState::Stopped);
+        }
+    }
+}
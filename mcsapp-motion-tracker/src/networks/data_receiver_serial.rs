@@ -0,0 +1,139 @@
+use super::data_broadcaster::{DataReceiverChannel, SubscriptionConfig};
+use super::data_receiver::{
+    is_timeout_error, DataReceiver, DataReceiverContext, DataReceiverCore, DataReceiverError, DataReceiverState,
+    DataReceiverStatistics, DataReceiverWorker, RECONNECT_MAX_BACKOFF, RECONNECT_MIN_BACKOFF,
+};
+use mcslib_common::serialport::{open_with_settings as open_serial_port, SerialPortSettings as SerialPortConfig};
+use mcslib_common::types::{SerialPortSettings as SPSettings, TrackerCommunication, TrackersConfig};
+use mcslib_protocol::FrameSplitter;
+use std::collections::HashMap;
+use std::io::Read;
+
+const SERIAL_READ_BUFFER_SIZE: usize = 1_024;
+
+#[derive(Debug)]
+pub struct DataReceiverSerial {
+    core: DataReceiverCore,
+    broadcaster_addresses: HashMap<String, SPSettings>,
+}
+
+impl DataReceiverSerial {
+    pub fn new(trackers_config: &TrackersConfig) -> DataReceiverSerial {
+        let broadcaster_addresses = trackers_config
+            .0
+            .iter()
+            .filter_map(|tracker_endpoint| match &tracker_endpoint.tracker_communication {
+                TrackerCommunication::SerialPort(sp_settings) => {
+                    Some((tracker_endpoint.tracker_name.clone(), sp_settings.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        DataReceiverSerial {
+            core: DataReceiverCore::new("Serial"),
+            broadcaster_addresses,
+        }
+    }
+
+    fn prepare_workers(&self) -> Result<Vec<(String, DataReceiverWorker)>, DataReceiverError> {
+        let workers = self
+            .broadcaster_addresses
+            .iter()
+            .map(|(tracker_name, sp_settings)| {
+                let worker_tracker_name = tracker_name.clone();
+                let (port_name, port_config) = sp_settings.get_sp_settings();
+                let worker: DataReceiverWorker =
+                    Box::new(move |context| receive_serial(worker_tracker_name, port_name, port_config, context));
+                (tracker_name.clone(), worker)
+            })
+            .collect();
+
+        Ok(workers)
+    }
+}
+
+impl DataReceiver for DataReceiverSerial {
+    fn start(&self) -> Result<(), DataReceiverError> {
+        self.core.start(|| self.prepare_workers())
+    }
+
+    fn stop(&self) -> Result<(), DataReceiverError> {
+        self.core.stop()
+    }
+
+    fn state(&self) -> DataReceiverState {
+        self.core.state()
+    }
+
+    fn subscribe_with(&self, subscription_config: SubscriptionConfig) -> DataReceiverChannel {
+        self.core.subscribe_with(subscription_config)
+    }
+
+    fn statistics(&self) -> DataReceiverStatistics {
+        self.core.statistics()
+    }
+}
+
+fn receive_serial(
+    tracker_name: String,
+    port_name: String,
+    port_config: SerialPortConfig,
+    context: DataReceiverContext,
+) {
+    let mut buffer = vec![0u8; SERIAL_READ_BUFFER_SIZE];
+    let mut frame_splitter = FrameSplitter::new();
+    let mut reopen_backoff = RECONNECT_MIN_BACKOFF;
+    let mut is_reopen = false;
+
+    while !context.is_stop_requested() {
+        let mut serial_port = match open_serial_port(&port_name, &port_config) {
+            Ok(serial_port) => serial_port,
+            Err(error) => {
+                warn!(
+                    "Serial open of \"{}\" ({}) failed: {}, retrying in {:?}",
+                    tracker_name, port_name, error, reopen_backoff
+                );
+                context.sleep_unless_stopped(reopen_backoff);
+                reopen_backoff = (reopen_backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                continue;
+            }
+        };
+
+        info!("Serial port opened for \"{}\" ({})", tracker_name, port_name);
+
+        if is_reopen {
+            context.record_reconnect();
+        }
+
+        is_reopen = true;
+        reopen_backoff = RECONNECT_MIN_BACKOFF;
+        frame_splitter.clear();
+
+        while !context.is_stop_requested() {
+            match serial_port.read(&mut buffer) {
+                Ok(0) => continue,
+                Ok(length) => {
+                    let discarded_bytes = frame_splitter.discarded_bytes();
+                    frame_splitter.extend(&buffer[..length]);
+
+                    while let Some(frame) = frame_splitter.next_frame() {
+                        context.publish(frame);
+                    }
+
+                    if frame_splitter.discarded_bytes() > discarded_bytes {
+                        context.record_decode_error();
+                    }
+                }
+                Err(ref error) if is_timeout_error(error) => continue,
+                Err(error) => {
+                    warn!(
+                        "Serial receive from \"{}\" ({}) failed: {}, reopening",
+                        tracker_name, port_name, error
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
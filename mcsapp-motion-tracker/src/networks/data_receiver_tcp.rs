@@ -0,0 +1,137 @@
+use super::data_broadcaster::{DataReceiverChannel, SubscriptionConfig};
+use super::data_receiver::{
+    is_timeout_error, DataReceiver, DataReceiverContext, DataReceiverCore, DataReceiverError, DataReceiverState,
+    DataReceiverStatistics, DataReceiverWorker, RECONNECT_MAX_BACKOFF, RECONNECT_MIN_BACKOFF,
+};
+use mcslib_common::types::{TrackerCommunication, TrackersConfig};
+use mcslib_protocol::FrameSplitter;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{SocketAddr, SocketAddrV4, TcpStream};
+use std::time::Duration;
+
+const TCP_READ_BUFFER_SIZE: usize = 4_096;
+const TCP_READ_TIMEOUT: Duration = Duration::from_millis(100);
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct DataReceiverTCP {
+    core: DataReceiverCore,
+    broadcaster_addresses: HashMap<String, SocketAddrV4>,
+}
+
+impl DataReceiverTCP {
+    pub fn new(trackers_config: &TrackersConfig) -> DataReceiverTCP {
+        let broadcaster_addresses = trackers_config
+            .0
+            .iter()
+            .filter_map(|tracker_endpoint| match tracker_endpoint.tracker_communication {
+                TrackerCommunication::TCP(address) => Some((tracker_endpoint.tracker_name.clone(), address)),
+                _ => None,
+            })
+            .collect();
+
+        DataReceiverTCP {
+            core: DataReceiverCore::new("TCP"),
+            broadcaster_addresses,
+        }
+    }
+
+    fn prepare_workers(&self) -> Result<Vec<(String, DataReceiverWorker)>, DataReceiverError> {
+        let workers = self
+            .broadcaster_addresses
+            .iter()
+            .map(|(tracker_name, address)| {
+                let worker_tracker_name = tracker_name.clone();
+                let address = SocketAddr::V4(*address);
+                let worker: DataReceiverWorker =
+                    Box::new(move |context| receive_tcp(worker_tracker_name, address, context));
+                (tracker_name.clone(), worker)
+            })
+            .collect();
+
+        Ok(workers)
+    }
+}
+
+impl DataReceiver for DataReceiverTCP {
+    fn start(&self) -> Result<(), DataReceiverError> {
+        self.core.start(|| self.prepare_workers())
+    }
+
+    fn stop(&self) -> Result<(), DataReceiverError> {
+        self.core.stop()
+    }
+
+    fn state(&self) -> DataReceiverState {
+        self.core.state()
+    }
+
+    fn subscribe_with(&self, subscription_config: SubscriptionConfig) -> DataReceiverChannel {
+        self.core.subscribe_with(subscription_config)
+    }
+
+    fn statistics(&self) -> DataReceiverStatistics {
+        self.core.statistics()
+    }
+}
+
+fn receive_tcp(tracker_name: String, address: SocketAddr, context: DataReceiverContext) {
+    let mut buffer = vec![0u8; TCP_READ_BUFFER_SIZE];
+    let mut frame_splitter = FrameSplitter::new();
+    let mut reconnect_backoff = RECONNECT_MIN_BACKOFF;
+    let mut is_reconnect = false;
+
+    while !context.is_stop_requested() {
+        let mut stream = match TcpStream::connect_timeout(&address, TCP_CONNECT_TIMEOUT)
+            .and_then(|stream| stream.set_read_timeout(Some(TCP_READ_TIMEOUT)).map(|_| stream))
+        {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!(
+                    "TCP connect to \"{}\" ({}) failed: {}, retrying in {:?}",
+                    tracker_name, address, error, reconnect_backoff
+                );
+                context.sleep_unless_stopped(reconnect_backoff);
+                reconnect_backoff = (reconnect_backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                continue;
+            }
+        };
+
+        info!("TCP connected to \"{}\" ({})", tracker_name, address);
+
+        if is_reconnect {
+            context.record_reconnect();
+        }
+
+        is_reconnect = true;
+        reconnect_backoff = RECONNECT_MIN_BACKOFF;
+        frame_splitter.clear();
+
+        while !context.is_stop_requested() {
+            match stream.read(&mut buffer) {
+                Ok(0) => {
+                    warn!("TCP connection to \"{}\" ({}) closed by peer", tracker_name, address);
+                    break;
+                }
+                Ok(length) => {
+                    let discarded_bytes = frame_splitter.discarded_bytes();
+                    frame_splitter.extend(&buffer[..length]);
+
+                    while let Some(frame) = frame_splitter.next_frame() {
+                        context.publish(frame);
+                    }
+
+                    if frame_splitter.discarded_bytes() > discarded_bytes {
+                        context.record_decode_error();
+                    }
+                }
+                Err(ref error) if is_timeout_error(error) => continue,
+                Err(error) => {
+                    warn!("TCP receive from \"{}\" ({}) failed: {}", tracker_name, address, error);
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use super::data_broadcaster::{DataReceiverChannel, SubscriptionConfig};
+use super::data_receiver::{
+    is_timeout_error, DataReceiver, DataReceiverContext, DataReceiverCore, DataReceiverError, DataReceiverState,
+    DataReceiverStatistics, DataReceiverWorker, RECONNECT_MAX_BACKOFF, RECONNECT_MIN_BACKOFF,
+};
+use mcslib_common::bytes::Bytes;
+use mcslib_common::types::{TrackerCommunication, TrackersConfig};
+use std::collections::HashMap;
+use std::net::{SocketAddrV4, UdpSocket};
+use std::time::Duration;
+
+const UDP_MAX_DATAGRAM_SIZE: usize = 65_507;
+const UDP_READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub struct DataReceiverUDP {
+    core: DataReceiverCore,
+    broadcaster_addresses: HashMap<String, SocketAddrV4>,
+}
+
+impl DataReceiverUDP {
+    pub fn new(trackers_config: &TrackersConfig) -> DataReceiverUDP {
+        let broadcaster_addresses = trackers_config
+            .0
+            .iter()
+            .filter_map(|tracker_endpoint| match tracker_endpoint.tracker_communication {
+                TrackerCommunication::UDP(address) => Some((tracker_endpoint.tracker_name.clone(), address)),
+                _ => None,
+            })
+            .collect();
+
+        DataReceiverUDP {
+            core: DataReceiverCore::new("UDP"),
+            broadcaster_addresses,
+        }
+    }
+
+    fn bind_sockets(&self) -> Result<Vec<(String, DataReceiverWorker)>, DataReceiverError> {
+        let mut workers = Vec::with_capacity(self.broadcaster_addresses.len());
+
+        for (tracker_name, address) in &self.broadcaster_addresses {
+            let socket = UdpSocket::bind(address)
+                .and_then(|socket| socket.set_read_timeout(Some(UDP_READ_TIMEOUT)).map(|_| socket))
+                .map_err(|error| {
+                    DataReceiverError::IOError(format!("Cannot bind \"{}\" to {}", tracker_name, address), error)
+                })?;
+            let worker_tracker_name = tracker_name.clone();
+            let worker: DataReceiverWorker = Box::new(move |context| receive_udp(worker_tracker_name, socket, context));
+            workers.push((tracker_name.clone(), worker));
+        }
+
+        Ok(workers)
+    }
+}
+
+impl DataReceiver for DataReceiverUDP {
+    fn start(&self) -> Result<(), DataReceiverError> {
+        self.core.start(|| self.bind_sockets())
+    }
+
+    fn stop(&self) -> Result<(), DataReceiverError> {
+        self.core.stop()
+    }
+
+    fn state(&self) -> DataReceiverState {
+        self.core.state()
+    }
+
+    fn subscribe_with(&self, subscription_config: SubscriptionConfig) -> DataReceiverChannel {
+        self.core.subscribe_with(subscription_config)
+    }
+
+    fn statistics(&self) -> DataReceiverStatistics {
+        self.core.statistics()
+    }
+}
+
+fn receive_udp(tracker_name: String, socket: UdpSocket, context: DataReceiverContext) {
+    let mut buffer = vec![0u8; UDP_MAX_DATAGRAM_SIZE];
+    let mut error_backoff = RECONNECT_MIN_BACKOFF;
+
+    while !context.is_stop_requested() {
+        match socket.recv_from(&mut buffer) {
+            Ok((length, _)) => {
+                error_backoff = RECONNECT_MIN_BACKOFF;
+                context.publish(Bytes::copy_from_slice(&buffer[..length]));
+            }
+            Err(ref error) if is_timeout_error(error) => continue,
+            Err(error) => {
+                // Errors such as ICMP port unreachable come back immediately, so retrying at once would spin
+                error!(
+                    "UDP receive from \"{}\" failed: {}, retrying in {:?}",
+                    tracker_name, error, error_backoff
+                );
+                context.record_receive_error();
+                context.sleep_unless_stopped(error_backoff);
+                error_backoff = (error_backoff * 2).min(RECONNECT_MAX_BACKOFF);
+            }
+        }
+    }
+}
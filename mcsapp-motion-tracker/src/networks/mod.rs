@@ -1,3 +1,13 @@
+mod data_broadcaster;
 mod data_receiver;
+mod data_receiver_serial;
+mod data_receiver_tcp;
+mod data_receiver_udp;
+mod receiver_stats;
 
+pub use data_broadcaster::*;
 pub use data_receiver::*;
+pub use data_receiver_serial::*;
+pub use data_receiver_tcp::*;
+pub use data_receiver_udp::*;
+pub use receiver_stats::*;
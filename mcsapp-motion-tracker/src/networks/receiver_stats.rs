@@ -0,0 +1,177 @@
+use std::fmt::{Display, Formatter, Result as FormatterResult};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Point-in-time statistics of one tracker feeding a receiver.
+#[derive(Debug, Clone)]
+pub struct ReceiverStats {
+    pub tracker_name: String,
+    pub packets_received: usize,
+    pub bytes_received: usize,
+    pub packets_per_second: f64,
+    pub last_packet_age: Option<Duration>,
+    pub decode_errors: usize,
+    pub receive_errors: usize,
+    pub sequence_gaps: usize,
+    pub reconnect_count: usize,
+}
+
+#[derive(Debug, Default)]
+struct TrackerStatsState {
+    packets_received: usize,
+    bytes_received: usize,
+    decode_errors: usize,
+    receive_errors: usize,
+    sequence_gaps: usize,
+    reconnect_count: usize,
+    last_packet_instant: Option<Instant>,
+    last_sequence_number: Option<u32>,
+    rate_window_start: Option<Instant>,
+    rate_window_packets: usize,
+    packets_per_second: f64,
+}
+
+/// Live counters updated by a receiver worker thread.
+#[derive(Debug)]
+pub struct TrackerStats {
+    tracker_name: String,
+    state: Mutex<TrackerStatsState>,
+}
+
+impl Display for ReceiverStats {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        let last_packet_age = match self.last_packet_age {
+            Some(last_packet_age) => format!("{:.3}s", last_packet_age.as_secs_f64()),
+            None => "never".into(),
+        };
+
+        write!(
+            formatter,
+            "\"{}\": {} packet(s), {} byte(s), {:.1} packet/s, last packet {}, {} decode error(s), \
+             {} receive error(s), {} sequence gap(s), {} reconnect(s)",
+            self.tracker_name,
+            self.packets_received,
+            self.bytes_received,
+            self.packets_per_second,
+            last_packet_age,
+            self.decode_errors,
+            self.receive_errors,
+            self.sequence_gaps,
+            self.reconnect_count
+        )
+    }
+}
+
+impl TrackerStats {
+    pub fn new(tracker_name: &str) -> TrackerStats {
+        TrackerStats {
+            tracker_name: tracker_name.into(),
+            state: Default::default(),
+        }
+    }
+
+    pub fn tracker_name(&self) -> &str {
+        &self.tracker_name
+    }
+
+    pub fn record_packet(&self, length: usize, sequence_number: u32) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.packets_received += 1;
+        state.bytes_received += length;
+        state.last_packet_instant = Some(now);
+
+        let is_newer_sequence = match state.last_sequence_number {
+            Some(last_sequence_number) => {
+                // A step of zero or beyond half the sequence space is a duplicated or reordered packet, not a gap
+                let sequence_step = sequence_number.wrapping_sub(last_sequence_number);
+                let is_newer_sequence = sequence_step > 0 && sequence_step < u32::MAX / 2;
+
+                if is_newer_sequence && sequence_step > 1 {
+                    state.sequence_gaps += 1;
+                }
+
+                is_newer_sequence
+            }
+            None => true,
+        };
+
+        if is_newer_sequence {
+            state.last_sequence_number = Some(sequence_number);
+        }
+
+        state.rate_window_packets += 1;
+        let rate_window_start = *state.rate_window_start.get_or_insert(now);
+        let rate_window_elapsed = now.duration_since(rate_window_start);
+
+        if rate_window_elapsed >= RATE_WINDOW {
+            state.packets_per_second = state.rate_window_packets as f64 / rate_window_elapsed.as_secs_f64();
+            state.rate_window_start = Some(now);
+            state.rate_window_packets = 0;
+        }
+    }
+
+    pub fn record_decode_error(&self) {
+        self.state.lock().unwrap().decode_errors += 1;
+    }
+
+    pub fn record_receive_error(&self) {
+        self.state.lock().unwrap().receive_errors += 1;
+    }
+
+    pub fn record_reconnect(&self) {
+        self.state.lock().unwrap().reconnect_count += 1;
+    }
+
+    pub fn snapshot(&self) -> ReceiverStats {
+        let state = self.state.lock().unwrap();
+        let last_packet_age = state.last_packet_instant.map(|instant| instant.elapsed());
+        let packets_per_second = match last_packet_age {
+            Some(last_packet_age) if last_packet_age < RATE_WINDOW * 2 => state.packets_per_second,
+            _ => 0.0,
+        };
+
+        ReceiverStats {
+            tracker_name: self.tracker_name.clone(),
+            packets_received: state.packets_received,
+            bytes_received: state.bytes_received,
+            packets_per_second,
+            last_packet_age,
+            decode_errors: state.decode_errors,
+            receive_errors: state.receive_errors,
+            sequence_gaps: state.sequence_gaps,
+            reconnect_count: state.reconnect_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracker_stats_counts_sequence_gaps() {
+        let tracker_stats = TrackerStats::new("HeadTracker");
+        assert!(tracker_stats.snapshot().last_packet_age.is_none());
+
+        for sequence_number in &[u32::MAX - 1, u32::MAX, 0, 3, 4, 2, 4, 10] {
+            tracker_stats.record_packet(10, *sequence_number);
+        }
+
+        tracker_stats.record_decode_error();
+        tracker_stats.record_receive_error();
+        tracker_stats.record_receive_error();
+        tracker_stats.record_reconnect();
+
+        let receiver_stats = tracker_stats.snapshot();
+        assert_eq!(receiver_stats.packets_received, 8);
+        assert_eq!(receiver_stats.bytes_received, 80);
+        assert_eq!(receiver_stats.sequence_gaps, 2);
+        assert_eq!(receiver_stats.decode_errors, 1);
+        assert_eq!(receiver_stats.receive_errors, 2);
+        assert_eq!(receiver_stats.reconnect_count, 1);
+        assert!(receiver_stats.last_packet_age.is_some());
+    }
+}
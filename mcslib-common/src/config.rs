@@ -1,9 +1,26 @@
-use crate::types::{JsonSerializable, TrackersServerConfig};
+use crate::geometry::{cross, distance, norm, subtract};
+use crate::types::{
+    BaseStations, BaseStationsConfig, BlobDetectorConfig, CalibrationObservation, CameraCalibration, CameraIntrinsics,
+    JsonSerializable, PnPMethod, PoseFilterConfig, PosePredictorConfig, PoseSolverConfig, RansacConfig, SafePoint3D,
+    TrackerEndpoint, TrackersConfig, TrackersServerConfig,
+};
 use std::fs::{read_to_string, write};
 use std::io::{Error as IOError, ErrorKind as IOErrorKind, Result as IOResult};
 use std::path::Path;
 
-pub trait ConfigLoader<T: for<'a> JsonSerializable<'a, T> + Default> {
+pub const MIN_MARKER_CONSTELLATION_POINTS: usize = 4;
+/// Largest calibration observation coordinate accepted, in the pose convention's length unit. Anything further is a
+/// corrupt measurement, and its squares would overflow the alignment.
+pub const MAX_CALIBRATION_COORDINATE: f64 = 1e6;
+/// Largest deviation of an `IPPESquare` corner from its ideal position, relative to half the side length.
+const IPPE_SQUARE_TOLERANCE: f64 = 1e-6;
+const DEGENERACY_TOLERANCE: f64 = 1e-6;
+
+pub trait ConfigValidation {
+    fn validate(&self) -> IOResult<()>;
+}
+
+pub trait ConfigLoader<T: for<'a> JsonSerializable<'a, T> + ConfigValidation + Default> {
     fn init_default(config_path: &str) -> IOResult<()> {
         let path = Path::new(config_path);
         let config_exists = path.exists();
@@ -32,9 +49,582 @@ pub trait ConfigLoader<T: for<'a> JsonSerializable<'a, T> + Default> {
 
         let config_json = read_to_string(path)?;
         let config_data = T::from_json(&config_json)?;
+        config_data.validate()?;
 
         Ok(config_data)
     }
+
+    /// Overwrites `config_path`, refusing configs that would not load back.
+    fn save_config(config_data: &T, config_path: &str) -> IOResult<()> {
+        config_data.validate()?;
+        write(Path::new(config_path), config_data.to_json())
+    }
+}
+
+pub fn invalid_config_error(message: String) -> IOError {
+    IOError::new(IOErrorKind::InvalidData, message)
+}
+
+/// Rejects constellations `solve_pnp` cannot resolve: fewer than four points, coincident points or all points on
+/// one line. Coplanar constellations are fine.
+pub fn validate_marker_constellation(marker_constellation: &[SafePoint3D]) -> IOResult<()> {
+    if marker_constellation.len() < MIN_MARKER_CONSTELLATION_POINTS {
+        return Err(invalid_config_error(format!(
+            "Marker constellation needs at least {} points, got {}",
+            MIN_MARKER_CONSTELLATION_POINTS,
+            marker_constellation.len()
+        )));
+    }
+
+    if marker_constellation
+        .iter()
+        .any(|point| !(point.x.is_finite() && point.y.is_finite() && point.z.is_finite()))
+    {
+        return Err(invalid_config_error(
+            "Marker constellation points must be finite".into(),
+        ));
+    }
+
+    let origin = &marker_constellation[0];
+    let (farthest_point, extent) = marker_constellation
+        .iter()
+        .map(|point| (point, distance(origin, point)))
+        .fold((origin, 0.0), |farthest, candidate| {
+            if candidate.1 > farthest.1 {
+                candidate
+            } else {
+                farthest
+            }
+        });
+    let tolerance = extent * DEGENERACY_TOLERANCE;
+
+    for (index, point) in marker_constellation.iter().enumerate() {
+        for other_point in &marker_constellation[index + 1..] {
+            if distance(point, other_point) <= tolerance {
+                return Err(invalid_config_error(format!(
+                    "Marker constellation has coincident points at {}",
+                    point
+                )));
+            }
+        }
+    }
+
+    let axis = subtract(farthest_point, origin);
+    let is_collinear = marker_constellation
+        .iter()
+        .all(|point| norm(&cross(&axis, &subtract(point, origin))) <= tolerance * extent);
+
+    if is_collinear {
+        return Err(invalid_config_error("Marker constellation points are collinear".into()));
+    }
+
+    Ok(())
+}
+
+/// Checks that the PnP method can solve the given constellation.
+pub fn validate_pose_solver_config(
+    pose_solver_config: &PoseSolverConfig,
+    marker_constellation: &[SafePoint3D],
+) -> IOResult<()> {
+    if let Some(ransac_config) = &pose_solver_config.ransac {
+        ransac_config.validate()?;
+    }
+
+    let tracking_reprojection_threshold = pose_solver_config.tracking_reprojection_threshold;
+
+    if !(tracking_reprojection_threshold.is_finite() && tracking_reprojection_threshold > 0.0) {
+        return Err(invalid_config_error(format!(
+            "Tracking reprojection threshold must be finite and positive, got {}",
+            tracking_reprojection_threshold
+        )));
+    }
+
+    match pose_solver_config.pnp_method {
+        PnPMethod::P3P | PnPMethod::AP3P if pose_solver_config.ransac.is_none() && marker_constellation.len() != 4 => {
+            Err(invalid_config_error(format!(
+                "{:?} needs exactly 4 marker points without RANSAC, got {}",
+                pose_solver_config.pnp_method,
+                marker_constellation.len()
+            )))
+        }
+        PnPMethod::IPPESquare => validate_ippe_square(marker_constellation),
+        _ => Ok(()),
+    }
+}
+
+/// `IPPESquare` solves for the corners of a square of side `2h` centred on the origin of the Z=0 plane, in OpenCV's
+/// order: (-h, h), (h, h), (h, -h), (-h, -h). Any other layout gives a wrong pose without an error.
+fn validate_ippe_square(marker_constellation: &[SafePoint3D]) -> IOResult<()> {
+    let half_side = marker_constellation.first().map(|point| -point.x).unwrap_or_default();
+    let corners = [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)];
+    let tolerance = half_side * IPPE_SQUARE_TOLERANCE;
+    let is_square = marker_constellation.len() == corners.len()
+        && half_side.is_finite()
+        && half_side > 0.0
+        && marker_constellation
+            .iter()
+            .zip(corners.iter())
+            .all(|(point, (x_sign, y_sign))| {
+                (point.x - x_sign * half_side).abs() <= tolerance
+                    && (point.y - y_sign * half_side).abs() <= tolerance
+                    && point.z == 0.0
+            });
+
+    if is_square {
+        Ok(())
+    } else {
+        Err(invalid_config_error(format!(
+            "IPPESquare needs the 4 corners of a square centred on the origin of the Z=0 plane, ordered \
+             (-h, h, 0), (h, h, 0), (h, -h, 0), (-h, -h, 0), got {:?}",
+            marker_constellation
+        )))
+    }
+}
+
+fn validate_positive(name: &str, value: f64) -> IOResult<()> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(invalid_config_error(format!(
+            "{} must be finite and positive, got {}",
+            name, value
+        )))
+    }
+}
+
+impl ConfigValidation for PoseFilterConfig {
+    fn validate(&self) -> IOResult<()> {
+        match self {
+            PoseFilterConfig::None => Ok(()),
+            PoseFilterConfig::OneEuro(one_euro_filter_config) => {
+                validate_positive("One Euro MinCutoff", one_euro_filter_config.min_cutoff)?;
+                validate_positive("One Euro DerivativeCutoff", one_euro_filter_config.derivative_cutoff)?;
+
+                if one_euro_filter_config.beta.is_finite() && one_euro_filter_config.beta >= 0.0 {
+                    Ok(())
+                } else {
+                    Err(invalid_config_error(format!(
+                        "One Euro Beta must be finite and non-negative, got {}",
+                        one_euro_filter_config.beta
+                    )))
+                }
+            }
+            PoseFilterConfig::Kalman(kalman_filter_config) => {
+                validate_positive(
+                    "Kalman PositionProcessNoise",
+                    kalman_filter_config.position_process_noise,
+                )?;
+                validate_positive(
+                    "Kalman PositionMeasurementNoise",
+                    kalman_filter_config.position_measurement_noise,
+                )?;
+                validate_positive(
+                    "Kalman OrientationProcessNoise",
+                    kalman_filter_config.orientation_process_noise,
+                )?;
+                validate_positive(
+                    "Kalman OrientationMeasurementNoise",
+                    kalman_filter_config.orientation_measurement_noise,
+                )
+            }
+        }
+    }
+}
+
+impl ConfigValidation for BlobDetectorConfig {
+    fn validate(&self) -> IOResult<()> {
+        if !(0.0..=255.0).contains(&self.threshold) {
+            return Err(invalid_config_error(format!(
+                "Blob detector Threshold must be within 0-255, got {}",
+                self.threshold
+            )));
+        }
+
+        if !(self.min_area >= 0.0 && self.min_area <= self.max_area && self.max_area.is_finite()) {
+            return Err(invalid_config_error(format!(
+                "Blob detector areas must satisfy 0 <= MinArea <= MaxArea, got {} and {}",
+                self.min_area, self.max_area
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.min_circularity) {
+            return Err(invalid_config_error(format!(
+                "Blob detector MinCircularity must be within 0-1, got {}",
+                self.min_circularity
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigValidation for PosePredictorConfig {
+    fn validate(&self) -> IOResult<()> {
+        for (name, interval) in &[
+            ("MaxPredictionInterval", self.max_prediction_interval),
+            ("VelocityTimeConstant", self.velocity_time_constant),
+        ] {
+            if !interval.is_finite() || *interval < 0.0 {
+                return Err(invalid_config_error(format!(
+                    "Pose predictor {} must be finite and non-negative, got {}",
+                    name, interval
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigValidation for RansacConfig {
+    fn validate(&self) -> IOResult<()> {
+        if self.iterations_count == 0 {
+            return Err(invalid_config_error("RANSAC needs at least one iteration".into()));
+        }
+
+        if !(self.reprojection_error.is_finite() && self.reprojection_error > 0.0) {
+            return Err(invalid_config_error(format!(
+                "RANSAC reprojection error must be finite and positive, got {}",
+                self.reprojection_error
+            )));
+        }
+
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(invalid_config_error(format!(
+                "RANSAC confidence must be within (0, 1), got {}",
+                self.confidence
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigValidation for CameraIntrinsics {
+    fn validate(&self) -> IOResult<()> {
+        let is_focal_length_valid = |focal_length: f64| focal_length.is_finite() && focal_length > 0.0;
+        let is_principal_point_valid = |principal_point: f64| principal_point.is_finite() && principal_point >= 0.0;
+
+        if !is_focal_length_valid(self.fx) || !is_focal_length_valid(self.fy) {
+            return Err(invalid_config_error(format!(
+                "Focal length must be finite and positive, got fx={} fy={}",
+                self.fx, self.fy
+            )));
+        }
+
+        if !is_principal_point_valid(self.cx) || !is_principal_point_valid(self.cy) {
+            return Err(invalid_config_error(format!(
+                "Principal point must be finite and non-negative, got cx={} cy={}",
+                self.cx, self.cy
+            )));
+        }
+
+        if self
+            .distortion_coefficients
+            .iter()
+            .any(|coefficient| !coefficient.is_finite())
+        {
+            return Err(invalid_config_error(format!(
+                "Distortion coefficients must be finite, got {:?}",
+                self.distortion_coefficients
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigValidation for TrackerEndpoint {
+    fn validate(&self) -> IOResult<()> {
+        self.camera_intrinsics.validate().map_err(|error| {
+            invalid_config_error(format!(
+                "Tracker \"{}\" has invalid camera intrinsics: {}",
+                self.tracker_name, error
+            ))
+        })?;
+        validate_marker_constellation(&self.marker_constellation).map_err(|error| {
+            invalid_config_error(format!(
+                "Tracker \"{}\" has an invalid marker constellation: {}",
+                self.tracker_name, error
+            ))
+        })?;
+        validate_pose_solver_config(&self.pose_solver_config, &self.marker_constellation).map_err(|error| {
+            invalid_config_error(format!(
+                "Tracker \"{}\" has an invalid pose solver config: {}",
+                self.tracker_name, error
+            ))
+        })?;
+        self.pose_filter_config.validate().map_err(|error| {
+            invalid_config_error(format!(
+                "Tracker \"{}\" has an invalid pose filter config: {}",
+                self.tracker_name, error
+            ))
+        })?;
+        self.pose_predictor_config.validate().map_err(|error| {
+            invalid_config_error(format!(
+                "Tracker \"{}\" has an invalid pose predictor config: {}",
+                self.tracker_name, error
+            ))
+        })?;
+
+        match &self.blob_detector_config {
+            Some(blob_detector_config) => blob_detector_config.validate().map_err(|error| {
+                invalid_config_error(format!(
+                    "Tracker \"{}\" has an invalid blob detector config: {}",
+                    self.tracker_name, error
+                ))
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ConfigValidation for TrackersConfig {
+    fn validate(&self) -> IOResult<()> {
+        self.0.iter().try_for_each(ConfigValidation::validate)
+    }
+}
+
+impl ConfigValidation for CameraCalibration {
+    fn validate(&self) -> IOResult<()> {
+        self.camera_intrinsics.validate()?;
+
+        if self.rms_reprojection_error.is_finite() && self.rms_reprojection_error >= 0.0 {
+            Ok(())
+        } else {
+            Err(invalid_config_error(format!(
+                "Calibration RMS reprojection error must be finite and non-negative, got {}",
+                self.rms_reprojection_error
+            )))
+        }
+    }
+}
+
+impl ConfigValidation for BaseStations {
+    fn validate(&self) -> IOResult<()> {
+        let values = [
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.rotation.roll,
+            self.rotation.pitch,
+            self.rotation.yaw,
+        ];
+
+        if values.iter().all(|value| value.is_finite()) {
+            Ok(())
+        } else {
+            Err(invalid_config_error(
+                "Base station position and rotation must be finite".into(),
+            ))
+        }
+    }
+}
+
+impl ConfigValidation for CalibrationObservation {
+    fn validate(&self) -> IOResult<()> {
+        let position = [self.position.x, self.position.y, self.position.z];
+
+        if position
+            .iter()
+            .all(|value| value.is_finite() && value.abs() <= MAX_CALIBRATION_COORDINATE)
+        {
+            Ok(())
+        } else {
+            Err(invalid_config_error(format!(
+                "Calibration observation of placement {} from base station \"{}\" must lie within {} of the station, \
+                 got {:?}",
+                self.placement, self.station_name, MAX_CALIBRATION_COORDINATE, position
+            )))
+        }
+    }
+}
+
+impl ConfigValidation for BaseStationsConfig {
+    fn validate(&self) -> IOResult<()> {
+        for (index, base_station) in self.0.iter().enumerate() {
+            if base_station.station_name.trim().is_empty() {
+                return Err(invalid_config_error(format!(
+                    "Base station #{} has no StationName",
+                    index
+                )));
+            }
+
+            if self.0[..index]
+                .iter()
+                .any(|other_base_station| other_base_station.station_name == base_station.station_name)
+            {
+                return Err(invalid_config_error(format!(
+                    "Base station \"{}\" is defined more than once",
+                    base_station.station_name
+                )));
+            }
+
+            base_station.validate().map_err(|error| {
+                invalid_config_error(format!("Base station \"{}\": {}", base_station.station_name, error))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigValidation for TrackersServerConfig {
+    fn validate(&self) -> IOResult<()> {
+        self.base_stations_config.validate()?;
+        self.trackers_config.validate()
+    }
 }
 
 impl<'a> ConfigLoader<TrackersServerConfig> for TrackersServerConfig {}
+impl ConfigLoader<CameraCalibration> for CameraCalibration {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{default_marker_constellation, KalmanFilterConfig, OneEuroFilterConfig};
+
+    #[test]
+    fn camera_intrinsics_validation() {
+        assert!(TrackersServerConfig::default().validate().is_ok());
+
+        let mut tracker_endpoint = TrackerEndpoint::default();
+        tracker_endpoint.camera_intrinsics.fx = 0.0;
+        assert!(tracker_endpoint.validate().is_err());
+
+        tracker_endpoint.camera_intrinsics = Default::default();
+        tracker_endpoint.camera_intrinsics.cy = f64::NAN;
+        assert!(tracker_endpoint.validate().is_err());
+
+        tracker_endpoint.camera_intrinsics = Default::default();
+        tracker_endpoint.camera_intrinsics.distortion_coefficients[4] = f64::INFINITY;
+        assert!(tracker_endpoint.validate().is_err());
+    }
+
+    #[test]
+    fn marker_constellation_validation() {
+        let mut marker_constellation = default_marker_constellation();
+        assert!(validate_marker_constellation(&marker_constellation).is_ok());
+        assert!(validate_marker_constellation(&marker_constellation[..3]).is_err());
+
+        marker_constellation.push(marker_constellation[0].clone());
+        assert!(validate_marker_constellation(&marker_constellation).is_err());
+
+        let collinear_constellation: Vec<SafePoint3D> = (0..5)
+            .map(|index| SafePoint3D::new(index as f64, 2.0 * index as f64, -(index as f64)))
+            .collect();
+        assert!(validate_marker_constellation(&collinear_constellation).is_err());
+    }
+
+    #[test]
+    fn pose_solver_config_validation() {
+        let marker_constellation = default_marker_constellation();
+        let mut pose_solver_config = PoseSolverConfig {
+            pnp_method: PnPMethod::P3P,
+            ..Default::default()
+        };
+        assert!(validate_pose_solver_config(&pose_solver_config, &marker_constellation).is_ok());
+
+        let mut five_point_constellation = marker_constellation.clone();
+        five_point_constellation.push(SafePoint3D::new(0.0, 5.0, 0.0));
+        assert!(validate_pose_solver_config(&pose_solver_config, &five_point_constellation).is_err());
+
+        pose_solver_config.ransac = Some(Default::default());
+        assert!(validate_pose_solver_config(&pose_solver_config, &five_point_constellation).is_ok());
+
+        pose_solver_config.ransac = Some(RansacConfig {
+            confidence: 1.0,
+            ..Default::default()
+        });
+        assert!(validate_pose_solver_config(&pose_solver_config, &five_point_constellation).is_err());
+
+        // The default constellation lies on the XZ plane, not the Z=0 plane IPPE expects
+        pose_solver_config = PoseSolverConfig {
+            pnp_method: PnPMethod::IPPESquare,
+            ..Default::default()
+        };
+        assert!(validate_pose_solver_config(&pose_solver_config, &marker_constellation).is_err());
+
+        let square = vec![
+            SafePoint3D::new(-11.4, 11.4, 0.0),
+            SafePoint3D::new(11.4, 11.4, 0.0),
+            SafePoint3D::new(11.4, -11.4, 0.0),
+            SafePoint3D::new(-11.4, -11.4, 0.0),
+        ];
+        assert!(validate_pose_solver_config(&pose_solver_config, &square).is_ok());
+
+        let mut reordered_square = square.clone();
+        reordered_square.swap(1, 3);
+        assert!(validate_pose_solver_config(&pose_solver_config, &reordered_square).is_err());
+
+        let off_centre_square: Vec<SafePoint3D> = square
+            .iter()
+            .map(|point| SafePoint3D::new(point.x + 1.0, point.y, 0.0))
+            .collect();
+        assert!(validate_pose_solver_config(&pose_solver_config, &off_centre_square).is_err());
+
+        let rectangle: Vec<SafePoint3D> = square
+            .iter()
+            .map(|point| SafePoint3D::new(point.x, point.y * 2.0, 0.0))
+            .collect();
+        assert!(validate_pose_solver_config(&pose_solver_config, &rectangle).is_err());
+        assert!(validate_pose_solver_config(&pose_solver_config, &square[..3]).is_err());
+    }
+
+    #[test]
+    fn pose_filter_config_validation() {
+        assert!(PoseFilterConfig::None.validate().is_ok());
+        assert!(PoseFilterConfig::OneEuro(Default::default()).validate().is_ok());
+        assert!(PoseFilterConfig::Kalman(Default::default()).validate().is_ok());
+
+        let one_euro_filter_config = OneEuroFilterConfig {
+            beta: -1.0,
+            ..Default::default()
+        };
+        assert!(PoseFilterConfig::OneEuro(one_euro_filter_config).validate().is_err());
+
+        let kalman_filter_config = KalmanFilterConfig {
+            position_measurement_noise: 0.0,
+            ..Default::default()
+        };
+        assert!(PoseFilterConfig::Kalman(kalman_filter_config).validate().is_err());
+    }
+
+    #[test]
+    fn base_stations_config_migration() {
+        let legacy_json = r#"{
+            "StationA": {
+                "Position": { "X": 1.0, "Y": 2.0, "Z": 3.0 },
+                "Rotation": { "Roll": 0.0, "Pitch": 0.0, "Yaw": 90.0 }
+            },
+            "StationB": {
+                "Position": { "X": -1.0, "Y": 0.0, "Z": 0.0 },
+                "Rotation": { "Roll": 0.0, "Pitch": 0.0, "Yaw": 0.0 }
+            }
+        }"#;
+        let base_stations_config = BaseStationsConfig::from_json(legacy_json).unwrap();
+        assert_eq!(base_stations_config.0.len(), 2);
+        assert_eq!(base_stations_config.0[0].station_name, "StationA");
+        assert!((base_stations_config.0[0].rotation.yaw - 90.0).abs() < f64::EPSILON);
+        assert_eq!(base_stations_config.0[1].station_name, "StationB");
+        assert!((base_stations_config.0[1].position.x - -1.0).abs() < f64::EPSILON);
+        assert!(base_stations_config.validate().is_ok());
+
+        let base_stations_config = BaseStationsConfig::from_json(&base_stations_config.to_json()).unwrap();
+        assert_eq!(base_stations_config.0[1].station_name, "StationB");
+
+        let mut base_stations_config = BaseStationsConfig::default();
+        base_stations_config.0.push(BaseStations {
+            station_name: "StationA".into(),
+            ..Default::default()
+        });
+        assert!(base_stations_config.validate().is_err());
+
+        base_stations_config.0[2].station_name = " ".into();
+        assert!(base_stations_config.validate().is_err());
+
+        base_stations_config.0[2].station_name = "StationC".into();
+        base_stations_config.0[2].position.z = f64::INFINITY;
+        assert!(base_stations_config.validate().is_err());
+    }
+}
@@ -0,0 +1,608 @@
+use crate::types::{
+    AngleUnit, AxisConvention, Pose, PoseConvention, RotationMatrix, SafeEulerAngles, SafePoint3D, SafeQuaternion,
+};
+
+pub const IDENTITY_MATRIX: RotationMatrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+const GIMBAL_LOCK_TOLERANCE: f64 = 1e-9;
+const JACOBI_MAX_SWEEPS: usize = 64;
+
+pub fn subtract(a: &SafePoint3D, b: &SafePoint3D) -> SafePoint3D {
+    SafePoint3D::new(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+pub fn dot(a: &SafePoint3D, b: &SafePoint3D) -> f64 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+pub fn cross(a: &SafePoint3D, b: &SafePoint3D) -> SafePoint3D {
+    SafePoint3D::new(a.y * b.z - a.z * b.y, a.z * b.x - a.x * b.z, a.x * b.y - a.y * b.x)
+}
+
+pub fn norm(a: &SafePoint3D) -> f64 {
+    dot(a, a).sqrt()
+}
+
+pub fn distance(a: &SafePoint3D, b: &SafePoint3D) -> f64 {
+    norm(&subtract(a, b))
+}
+
+pub fn scale(a: &SafePoint3D, factor: f64) -> SafePoint3D {
+    SafePoint3D::new(a.x * factor, a.y * factor, a.z * factor)
+}
+
+pub fn multiply_matrices(a: &RotationMatrix, b: &RotationMatrix) -> RotationMatrix {
+    let mut product = [[0.0; 3]; 3];
+
+    for (row, product_row) in product.iter_mut().enumerate() {
+        for (col, value) in product_row.iter_mut().enumerate() {
+            *value = (0..3).map(|index| a[row][index] * b[index][col]).sum();
+        }
+    }
+
+    product
+}
+
+pub fn transpose(a: &RotationMatrix) -> RotationMatrix {
+    let mut transposed = [[0.0; 3]; 3];
+
+    for (row, values) in a.iter().enumerate() {
+        for (col, value) in values.iter().enumerate() {
+            transposed[col][row] = *value;
+        }
+    }
+
+    transposed
+}
+
+pub fn rotate(rotation_matrix: &RotationMatrix, point: &SafePoint3D) -> SafePoint3D {
+    let row_dot = |row: &[f64; 3]| row[0] * point.x + row[1] * point.y + row[2] * point.z;
+    SafePoint3D::new(
+        row_dot(&rotation_matrix[0]),
+        row_dot(&rotation_matrix[1]),
+        row_dot(&rotation_matrix[2]),
+    )
+}
+
+/// Rodrigues' formula, the same mapping as OpenCV's `rodrigues`.
+pub fn rotation_matrix_from_rotation_vector(rotation_vector: &[f64; 3]) -> RotationMatrix {
+    let theta = (rotation_vector[0].powi(2) + rotation_vector[1].powi(2) + rotation_vector[2].powi(2)).sqrt();
+
+    if theta < f64::EPSILON {
+        return IDENTITY_MATRIX;
+    }
+
+    let (kx, ky, kz) = (
+        rotation_vector[0] / theta,
+        rotation_vector[1] / theta,
+        rotation_vector[2] / theta,
+    );
+    let (sin_theta, cos_theta) = theta.sin_cos();
+    let one_minus_cos = 1.0 - cos_theta;
+
+    [
+        [
+            cos_theta + kx * kx * one_minus_cos,
+            kx * ky * one_minus_cos - kz * sin_theta,
+            kx * kz * one_minus_cos + ky * sin_theta,
+        ],
+        [
+            ky * kx * one_minus_cos + kz * sin_theta,
+            cos_theta + ky * ky * one_minus_cos,
+            ky * kz * one_minus_cos - kx * sin_theta,
+        ],
+        [
+            kz * kx * one_minus_cos - ky * sin_theta,
+            kz * ky * one_minus_cos + kx * sin_theta,
+            cos_theta + kz * kz * one_minus_cos,
+        ],
+    ]
+}
+
+pub fn normalize_quaternion(quaternion: &SafeQuaternion) -> SafeQuaternion {
+    let length = (quaternion.w.powi(2) + quaternion.x.powi(2) + quaternion.y.powi(2) + quaternion.z.powi(2)).sqrt();
+
+    if length < f64::EPSILON {
+        return Default::default();
+    }
+
+    SafeQuaternion {
+        w: quaternion.w / length,
+        x: quaternion.x / length,
+        y: quaternion.y / length,
+        z: quaternion.z / length,
+    }
+}
+
+/// Shepperd's method. The result is normalised with a non-negative `w`.
+pub fn quaternion_from_rotation_matrix(m: &RotationMatrix) -> SafeQuaternion {
+    let trace = m[0][0] + m[1][1] + m[2][2];
+    let quaternion = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        SafeQuaternion {
+            w: 0.25 * s,
+            x: (m[2][1] - m[1][2]) / s,
+            y: (m[0][2] - m[2][0]) / s,
+            z: (m[1][0] - m[0][1]) / s,
+        }
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+        SafeQuaternion {
+            w: (m[2][1] - m[1][2]) / s,
+            x: 0.25 * s,
+            y: (m[0][1] + m[1][0]) / s,
+            z: (m[0][2] + m[2][0]) / s,
+        }
+    } else if m[1][1] > m[2][2] {
+        let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+        SafeQuaternion {
+            w: (m[0][2] - m[2][0]) / s,
+            x: (m[0][1] + m[1][0]) / s,
+            y: 0.25 * s,
+            z: (m[1][2] + m[2][1]) / s,
+        }
+    } else {
+        let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+        SafeQuaternion {
+            w: (m[1][0] - m[0][1]) / s,
+            x: (m[0][2] + m[2][0]) / s,
+            y: (m[1][2] + m[2][1]) / s,
+            z: 0.25 * s,
+        }
+    };
+    let quaternion = normalize_quaternion(&quaternion);
+
+    if quaternion.w < 0.0 {
+        SafeQuaternion {
+            w: -quaternion.w,
+            x: -quaternion.x,
+            y: -quaternion.y,
+            z: -quaternion.z,
+        }
+    } else {
+        quaternion
+    }
+}
+
+pub fn rotation_matrix_from_quaternion(quaternion: &SafeQuaternion) -> RotationMatrix {
+    let SafeQuaternion { w, x, y, z } = normalize_quaternion(quaternion);
+
+    [
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - z * w),
+            2.0 * (x * z + y * w),
+        ],
+        [
+            2.0 * (x * y + z * w),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - x * w),
+        ],
+        [
+            2.0 * (x * z - y * w),
+            2.0 * (y * z + x * w),
+            1.0 - 2.0 * (x * x + y * y),
+        ],
+    ]
+}
+
+pub fn multiply_quaternions(a: &SafeQuaternion, b: &SafeQuaternion) -> SafeQuaternion {
+    SafeQuaternion {
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+    }
+}
+
+pub fn conjugate_quaternion(quaternion: &SafeQuaternion) -> SafeQuaternion {
+    SafeQuaternion {
+        w: quaternion.w,
+        x: -quaternion.x,
+        y: -quaternion.y,
+        z: -quaternion.z,
+    }
+}
+
+pub fn quaternion_dot(a: &SafeQuaternion, b: &SafeQuaternion) -> f64 {
+    a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+/// Unit quaternion rotating by `|rotation_vector|` radians about `rotation_vector`.
+pub fn quaternion_from_rotation_vector(rotation_vector: &[f64; 3]) -> SafeQuaternion {
+    let angle = (rotation_vector[0].powi(2) + rotation_vector[1].powi(2) + rotation_vector[2].powi(2)).sqrt();
+
+    if angle < f64::EPSILON {
+        return normalize_quaternion(&SafeQuaternion {
+            w: 1.0,
+            x: rotation_vector[0] / 2.0,
+            y: rotation_vector[1] / 2.0,
+            z: rotation_vector[2] / 2.0,
+        });
+    }
+
+    let (sin_half_angle, cos_half_angle) = (angle / 2.0).sin_cos();
+    let factor = sin_half_angle / angle;
+
+    SafeQuaternion {
+        w: cos_half_angle,
+        x: rotation_vector[0] * factor,
+        y: rotation_vector[1] * factor,
+        z: rotation_vector[2] * factor,
+    }
+}
+
+/// Inverse of `quaternion_from_rotation_vector`, taking the shortest rotation.
+pub fn rotation_vector_from_quaternion(quaternion: &SafeQuaternion) -> [f64; 3] {
+    let quaternion = normalize_quaternion(quaternion);
+    let sign = if quaternion.w < 0.0 { -1.0 } else { 1.0 };
+    let vector_norm = (quaternion.x.powi(2) + quaternion.y.powi(2) + quaternion.z.powi(2)).sqrt();
+
+    if vector_norm < f64::EPSILON {
+        return [
+            2.0 * quaternion.x * sign,
+            2.0 * quaternion.y * sign,
+            2.0 * quaternion.z * sign,
+        ];
+    }
+
+    let angle = 2.0 * vector_norm.atan2(quaternion.w * sign);
+    let factor = sign * angle / vector_norm;
+
+    [quaternion.x * factor, quaternion.y * factor, quaternion.z * factor]
+}
+
+/// Angle in radians of the rotation taking `a` to `b`.
+pub fn quaternion_angle(a: &SafeQuaternion, b: &SafeQuaternion) -> f64 {
+    let delta = multiply_quaternions(&conjugate_quaternion(a), b);
+    let [x, y, z] = rotation_vector_from_quaternion(&delta);
+    (x * x + y * y + z * z).sqrt()
+}
+
+/// Spherical interpolation from `a` (`t = 0`) to `b` (`t = 1`) along the shortest arc.
+pub fn slerp(a: &SafeQuaternion, b: &SafeQuaternion, t: f64) -> SafeQuaternion {
+    let delta = multiply_quaternions(&conjugate_quaternion(a), b);
+    let [x, y, z] = rotation_vector_from_quaternion(&delta);
+    let step = quaternion_from_rotation_vector(&[x * t, y * t, z * t]);
+    normalize_quaternion(&multiply_quaternions(a, &step))
+}
+
+fn angle_from_radians(radians: f64, angle_unit: AngleUnit) -> f64 {
+    match angle_unit {
+        AngleUnit::Degrees => radians.to_degrees(),
+        AngleUnit::Radians => radians,
+    }
+}
+
+fn angle_to_radians(angle: f64, angle_unit: AngleUnit) -> f64 {
+    match angle_unit {
+        AngleUnit::Degrees => angle.to_radians(),
+        AngleUnit::Radians => angle,
+    }
+}
+
+/// Intrinsic Z-Y-X angles of `R = Rz(yaw) * Ry(pitch) * Rx(roll)`. At gimbal lock the roll is reported as zero.
+pub fn euler_angles_from_rotation_matrix(m: &RotationMatrix, angle_unit: AngleUnit) -> SafeEulerAngles {
+    let sin_pitch = (-m[2][0]).clamp(-1.0, 1.0);
+    let pitch = sin_pitch.asin();
+    let (roll, yaw) = if sin_pitch.abs() < 1.0 - GIMBAL_LOCK_TOLERANCE {
+        (m[2][1].atan2(m[2][2]), m[1][0].atan2(m[0][0]))
+    } else {
+        (0.0, (-m[0][1]).atan2(m[1][1]))
+    };
+
+    SafeEulerAngles {
+        roll: angle_from_radians(roll, angle_unit),
+        pitch: angle_from_radians(pitch, angle_unit),
+        yaw: angle_from_radians(yaw, angle_unit),
+    }
+}
+
+pub fn rotation_matrix_from_euler_angles(euler_angles: &SafeEulerAngles, angle_unit: AngleUnit) -> RotationMatrix {
+    let (sin_roll, cos_roll) = angle_to_radians(euler_angles.roll, angle_unit).sin_cos();
+    let (sin_pitch, cos_pitch) = angle_to_radians(euler_angles.pitch, angle_unit).sin_cos();
+    let (sin_yaw, cos_yaw) = angle_to_radians(euler_angles.yaw, angle_unit).sin_cos();
+
+    [
+        [
+            cos_yaw * cos_pitch,
+            cos_yaw * sin_pitch * sin_roll - sin_yaw * cos_roll,
+            cos_yaw * sin_pitch * cos_roll + sin_yaw * sin_roll,
+        ],
+        [
+            sin_yaw * cos_pitch,
+            sin_yaw * sin_pitch * sin_roll + cos_yaw * cos_roll,
+            sin_yaw * sin_pitch * cos_roll - cos_yaw * sin_roll,
+        ],
+        [-sin_pitch, cos_pitch * sin_roll, cos_pitch * cos_roll],
+    ]
+}
+
+/// Rows are the convention's axes expressed in the OpenCV camera frame.
+pub fn axis_convention_basis(axis_convention: AxisConvention) -> RotationMatrix {
+    match axis_convention {
+        AxisConvention::OpenCV => IDENTITY_MATRIX,
+        AxisConvention::ForwardRightDown => [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        AxisConvention::OpenGL => [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]],
+    }
+}
+
+/// Builds a `Pose` from a `solve_pnp` result, i.e. the marker constellation's rotation and translation in the OpenCV
+/// camera frame.
+pub fn pose_from_camera_frame(
+    rotation_matrix: &RotationMatrix,
+    translation: &SafePoint3D,
+    pose_convention: &PoseConvention,
+) -> Pose {
+    let basis = axis_convention_basis(pose_convention.axis_convention);
+    let rotation_matrix = multiply_matrices(&multiply_matrices(&basis, rotation_matrix), &transpose(&basis));
+    let position = scale(&rotate(&basis, translation), pose_convention.length_scale);
+
+    Pose {
+        position,
+        orientation: euler_angles_from_rotation_matrix(&rotation_matrix, pose_convention.angle_unit),
+        quaternion: quaternion_from_rotation_matrix(&rotation_matrix),
+        rotation_matrix,
+    }
+}
+
+/// Eigen decomposition of a symmetric matrix by cyclic Jacobi rotations. Eigenvalues are sorted in descending
+/// order and `eigenvectors[i]` belongs to `eigenvalues[i]`. A matrix holding NaN or infinity yields non-finite
+/// eigenvalues, which callers must check.
+pub fn symmetric_eigen(matrix: &[Vec<f64>]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let size = matrix.len();
+    let mut a = matrix.to_vec();
+    let mut v: Vec<Vec<f64>> = (0..size)
+        .map(|row| (0..size).map(|col| if row == col { 1.0 } else { 0.0 }).collect())
+        .collect();
+    let scale: f64 = a.iter().flatten().map(|value| value * value).sum();
+
+    for _ in 0..JACOBI_MAX_SWEEPS {
+        let off_diagonal: f64 = (0..size)
+            .flat_map(|p| (p + 1..size).map(move |q| (p, q)))
+            .map(|(p, q)| a[p][q] * a[p][q])
+            .sum();
+
+        if off_diagonal <= scale * f64::EPSILON * f64::EPSILON {
+            break;
+        }
+
+        for p in 0..size {
+            for q in p + 1..size {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+
+                let (head, tail) = a.split_at_mut(q);
+
+                for (apk, aqk) in head[p].iter_mut().zip(tail[0].iter_mut()) {
+                    let (old_apk, old_aqk) = (*apk, *aqk);
+                    *apk = c * old_apk - s * old_aqk;
+                    *aqk = s * old_apk + c * old_aqk;
+                }
+
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..size).collect();
+    order.sort_by(|left, right| a[*right][*right].total_cmp(&a[*left][*left]));
+
+    let eigenvalues = order.iter().map(|index| a[*index][*index]).collect();
+    let eigenvectors = order
+        .iter()
+        .map(|index| v.iter().map(|row| row[*index]).collect())
+        .collect();
+
+    (eigenvalues, eigenvectors)
+}
+
+/// Builds a `Pose` from a position and orientation already expressed in the target convention.
+pub fn pose_from_quaternion(position: SafePoint3D, quaternion: &SafeQuaternion, angle_unit: AngleUnit) -> Pose {
+    let rotation_matrix = rotation_matrix_from_quaternion(quaternion);
+
+    Pose {
+        position,
+        orientation: euler_angles_from_rotation_matrix(&rotation_matrix, angle_unit),
+        quaternion: quaternion_from_rotation_matrix(&rotation_matrix),
+        rotation_matrix,
+    }
+}
+
+pub fn centroid(points: &[SafePoint3D]) -> SafePoint3D {
+    let count = points.len().max(1) as f64;
+
+    SafePoint3D::new(
+        points.iter().map(|point| point.x).sum::<f64>() / count,
+        points.iter().map(|point| point.y).sum::<f64>() / count,
+        points.iter().map(|point| point.z).sum::<f64>() / count,
+    )
+}
+
+/// Horn's closed-form absolute orientation: the rotation `R` and translation `t` minimizing the squared distances
+/// between `R * source[i] + t` and `target[i]`. The points must not all lie on one line.
+pub fn rigid_transform_between(source: &[SafePoint3D], target: &[SafePoint3D]) -> (RotationMatrix, SafePoint3D) {
+    let source_centroid = centroid(source);
+    let target_centroid = centroid(target);
+    let mut s = [[0.0; 3]; 3];
+
+    for (source_point, target_point) in source.iter().zip(target.iter()) {
+        let a = subtract(source_point, &source_centroid);
+        let b = subtract(target_point, &target_centroid);
+        let (a, b) = ([a.x, a.y, a.z], [b.x, b.y, b.z]);
+
+        for (row, a_component) in s.iter_mut().zip(a.iter()) {
+            for (value, b_component) in row.iter_mut().zip(b.iter()) {
+                *value += a_component * b_component;
+            }
+        }
+    }
+
+    let n = vec![
+        vec![
+            s[0][0] + s[1][1] + s[2][2],
+            s[1][2] - s[2][1],
+            s[2][0] - s[0][2],
+            s[0][1] - s[1][0],
+        ],
+        vec![
+            s[1][2] - s[2][1],
+            s[0][0] - s[1][1] - s[2][2],
+            s[0][1] + s[1][0],
+            s[2][0] + s[0][2],
+        ],
+        vec![
+            s[2][0] - s[0][2],
+            s[0][1] + s[1][0],
+            -s[0][0] + s[1][1] - s[2][2],
+            s[1][2] + s[2][1],
+        ],
+        vec![
+            s[0][1] - s[1][0],
+            s[2][0] + s[0][2],
+            s[1][2] + s[2][1],
+            -s[0][0] - s[1][1] + s[2][2],
+        ],
+    ];
+    let (_, eigenvectors) = symmetric_eigen(&n);
+    let rotation_matrix = rotation_matrix_from_quaternion(&SafeQuaternion {
+        w: eigenvectors[0][0],
+        x: eigenvectors[0][1],
+        y: eigenvectors[0][2],
+        z: eigenvectors[0][3],
+    });
+    let rotated_centroid = rotate(&rotation_matrix, &source_centroid);
+
+    (rotation_matrix, subtract(&target_centroid, &rotated_centroid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f64 = 1e-9;
+
+    fn assert_matrix_eq(a: &RotationMatrix, b: &RotationMatrix) {
+        for row in 0..3 {
+            for col in 0..3 {
+                assert!((a[row][col] - b[row][col]).abs() < TOLERANCE, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_representations_round_trip() {
+        let rotation_matrix = rotation_matrix_from_rotation_vector(&[0.0, 0.0, std::f64::consts::FRAC_PI_2]);
+        assert_matrix_eq(&rotation_matrix, &[[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+
+        let euler_angles = euler_angles_from_rotation_matrix(&rotation_matrix, AngleUnit::Degrees);
+        assert!((euler_angles.yaw - 90.0).abs() < TOLERANCE);
+        assert!(euler_angles.roll.abs() < TOLERANCE && euler_angles.pitch.abs() < TOLERANCE);
+
+        for rotation_vector in &[[0.3, -1.2, 2.0], [2.9, 0.1, -0.4], [0.0, 3.1, 0.2]] {
+            let rotation_matrix = rotation_matrix_from_rotation_vector(rotation_vector);
+            let quaternion = quaternion_from_rotation_matrix(&rotation_matrix);
+            assert_matrix_eq(&rotation_matrix_from_quaternion(&quaternion), &rotation_matrix);
+
+            let euler_angles = euler_angles_from_rotation_matrix(&rotation_matrix, AngleUnit::Radians);
+            assert_matrix_eq(
+                &rotation_matrix_from_euler_angles(&euler_angles, AngleUnit::Radians),
+                &rotation_matrix,
+            );
+        }
+    }
+
+    #[test]
+    fn pose_follows_axis_convention() {
+        let pose_convention = PoseConvention {
+            axis_convention: AxisConvention::ForwardRightDown,
+            angle_unit: AngleUnit::Degrees,
+            length_scale: 0.1,
+        };
+        let rotation_matrix = rotation_matrix_from_rotation_vector(&[0.0, 0.0, 0.25]);
+        let pose = pose_from_camera_frame(&rotation_matrix, &SafePoint3D::new(10.0, 20.0, 300.0), &pose_convention);
+        assert!((pose.position.x - 30.0).abs() < TOLERANCE);
+        assert!((pose.position.y - 1.0).abs() < TOLERANCE);
+        assert!((pose.position.z - 2.0).abs() < TOLERANCE);
+
+        // A roll about the camera's Z axis is a roll about the forward X axis
+        assert!((pose.orientation.roll - 0.25f64.to_degrees()).abs() < TOLERANCE);
+        assert!(pose.orientation.pitch.abs() < TOLERANCE && pose.orientation.yaw.abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn symmetric_eigen_decomposes() {
+        let matrix = vec![
+            vec![4.0, 1.0, -2.0, 2.0],
+            vec![1.0, 2.0, 0.0, 1.0],
+            vec![-2.0, 0.0, 3.0, -2.0],
+            vec![2.0, 1.0, -2.0, -1.0],
+        ];
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&matrix);
+        assert!(eigenvalues.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!((eigenvalues.iter().sum::<f64>() - 8.0).abs() < TOLERANCE);
+
+        for (eigenvalue, eigenvector) in eigenvalues.iter().zip(&eigenvectors) {
+            for (row, values) in matrix.iter().enumerate() {
+                let product: f64 = values.iter().zip(eigenvector).map(|(a, b)| a * b).sum();
+                assert!((product - eigenvalue * eigenvector[row]).abs() < 1e-9);
+            }
+        }
+
+        let (eigenvalues, _) = symmetric_eigen(&[vec![f64::NAN, 0.0], vec![0.0, 1.0]]);
+        assert!(eigenvalues.iter().any(|eigenvalue| eigenvalue.is_nan()));
+
+        let source = vec![
+            SafePoint3D::new(0.0, 0.0, 0.0),
+            SafePoint3D::new(1.0, 0.0, 0.0),
+            SafePoint3D::new(0.0, f64::NAN, 0.0),
+        ];
+        let (_, translation) = rigid_transform_between(&source, &source);
+        assert!(!translation.x.is_finite());
+    }
+
+    #[test]
+    fn quaternion_operations() {
+        let a = quaternion_from_rotation_vector(&[0.2, -0.1, 0.4]);
+        let b = quaternion_from_rotation_vector(&[-0.3, 0.5, 0.1]);
+        let [x, y, z] = rotation_vector_from_quaternion(&a);
+        assert!((x - 0.2).abs() < TOLERANCE && (y + 0.1).abs() < TOLERANCE && (z - 0.4).abs() < TOLERANCE);
+
+        assert_matrix_eq(
+            &rotation_matrix_from_quaternion(&multiply_quaternions(&a, &b)),
+            &multiply_matrices(
+                &rotation_matrix_from_quaternion(&a),
+                &rotation_matrix_from_quaternion(&b),
+            ),
+        );
+
+        let halfway = slerp(&a, &b, 0.5);
+        let total_angle = quaternion_angle(&a, &b);
+        assert!((quaternion_angle(&a, &halfway) - total_angle / 2.0).abs() < TOLERANCE);
+        assert!((quaternion_angle(&halfway, &b) - total_angle / 2.0).abs() < TOLERANCE);
+
+        // q and -q are the same rotation
+        let negated_b = SafeQuaternion {
+            w: -b.w,
+            x: -b.x,
+            y: -b.y,
+            z: -b.z,
+        };
+        assert!((quaternion_angle(&a, &negated_b) - total_angle).abs() < TOLERANCE);
+    }
+}
@@ -13,7 +13,13 @@ use env_logger::builder as log_builder;
 use std::env::set_var as set_env_var;
 
 pub mod config;
+pub mod geometry;
+pub mod pose_filter;
+pub mod pose_fusion;
+pub mod pose_predictor;
+pub mod station_calibration;
 pub mod types;
+pub mod world_frame;
 
 pub fn exit_with_error(error_message: &str) -> ! {
     error!("{}", error_message);
@@ -0,0 +1,409 @@
+use crate::geometry::{
+    conjugate_quaternion, multiply_quaternions, normalize_quaternion, pose_from_quaternion, quaternion_angle,
+    quaternion_from_rotation_vector, rotation_vector_from_quaternion, slerp,
+};
+use crate::types::{
+    AngleUnit, KalmanFilterConfig, OneEuroFilterConfig, Pose, PoseFilterConfig, SafePoint3D, SafeQuaternion,
+};
+use std::f64::consts::PI;
+
+pub const NANOS_PER_SECOND: f64 = 1_000_000_000.0;
+const INITIAL_VELOCITY_VARIANCE: f64 = 1e4;
+
+/// Smooths a stream of poses of one tracker. Timestamps are in nanoseconds; a pose that does not move time forward
+/// is ignored and the last filtered pose is returned again.
+pub trait PoseFilter: Send {
+    fn filter(&mut self, pose: &Pose, timestamp_nanos: i64) -> Pose;
+    fn reset(&mut self);
+}
+
+/// `PoseFilterConfig::None`, returns every pose unchanged.
+#[derive(Debug, Default)]
+pub struct PassThroughPoseFilter;
+
+#[derive(Debug)]
+pub struct OneEuroPoseFilter {
+    config: OneEuroFilterConfig,
+    angle_unit: AngleUnit,
+    last_timestamp_nanos: Option<i64>,
+    last_pose: Option<Pose>,
+    position: [f64; 3],
+    position_derivative: [f64; 3],
+    orientation: SafeQuaternion,
+    angular_speed: f64,
+}
+
+/// Per-axis position and velocity with their covariance, under a constant-velocity model.
+#[derive(Debug, Clone, Copy)]
+struct ConstantVelocityAxis {
+    position: f64,
+    velocity: f64,
+    covariance: [[f64; 2]; 2],
+}
+
+/// Position is filtered per axis. Orientation is filtered as a rotation-vector error around the current quaternion
+/// estimate with an angular velocity per axis, so it never leaves the unit sphere.
+#[derive(Debug)]
+pub struct KalmanPoseFilter {
+    config: KalmanFilterConfig,
+    angle_unit: AngleUnit,
+    last_timestamp_nanos: Option<i64>,
+    last_pose: Option<Pose>,
+    position_axes: [ConstantVelocityAxis; 3],
+    orientation: SafeQuaternion,
+    orientation_axes: [ConstantVelocityAxis; 3],
+}
+
+pub fn create_pose_filter(pose_filter_config: &PoseFilterConfig, angle_unit: AngleUnit) -> Box<dyn PoseFilter> {
+    match pose_filter_config {
+        PoseFilterConfig::None => Box::new(PassThroughPoseFilter),
+        PoseFilterConfig::OneEuro(one_euro_filter_config) => {
+            Box::new(OneEuroPoseFilter::new(one_euro_filter_config.clone(), angle_unit))
+        }
+        PoseFilterConfig::Kalman(kalman_filter_config) => {
+            Box::new(KalmanPoseFilter::new(kalman_filter_config.clone(), angle_unit))
+        }
+    }
+}
+
+fn point_to_array(point: &SafePoint3D) -> [f64; 3] {
+    [point.x, point.y, point.z]
+}
+
+fn array_to_point(array: &[f64; 3]) -> SafePoint3D {
+    SafePoint3D::new(array[0], array[1], array[2])
+}
+
+/// Seconds since the previous pose, or `None` when time did not move forward.
+fn elapsed_seconds(last_timestamp_nanos: Option<i64>, timestamp_nanos: i64) -> Option<f64> {
+    last_timestamp_nanos
+        .map(|last_timestamp_nanos| (timestamp_nanos - last_timestamp_nanos) as f64 / NANOS_PER_SECOND)
+        .filter(|elapsed_seconds| *elapsed_seconds > 0.0)
+}
+
+impl PoseFilter for PassThroughPoseFilter {
+    fn filter(&mut self, pose: &Pose, _timestamp_nanos: i64) -> Pose {
+        pose.clone()
+    }
+
+    fn reset(&mut self) {}
+}
+
+impl OneEuroPoseFilter {
+    pub fn new(config: OneEuroFilterConfig, angle_unit: AngleUnit) -> OneEuroPoseFilter {
+        OneEuroPoseFilter {
+            config,
+            angle_unit,
+            last_timestamp_nanos: None,
+            last_pose: None,
+            position: [0.0; 3],
+            position_derivative: [0.0; 3],
+            orientation: Default::default(),
+            angular_speed: 0.0,
+        }
+    }
+
+    fn smoothing_factor(cutoff: f64, elapsed_seconds: f64) -> f64 {
+        let time_constant = 1.0 / (2.0 * PI * cutoff);
+        1.0 / (1.0 + time_constant / elapsed_seconds)
+    }
+
+    fn cutoff(&self, speed: f64) -> f64 {
+        self.config.min_cutoff + self.config.beta * speed.abs()
+    }
+}
+
+impl PoseFilter for OneEuroPoseFilter {
+    fn filter(&mut self, pose: &Pose, timestamp_nanos: i64) -> Pose {
+        let elapsed_seconds = match (
+            elapsed_seconds(self.last_timestamp_nanos, timestamp_nanos),
+            &self.last_pose,
+        ) {
+            (Some(elapsed_seconds), Some(_)) => elapsed_seconds,
+            (None, Some(last_pose)) => return last_pose.clone(),
+            (_, None) => {
+                self.last_timestamp_nanos = Some(timestamp_nanos);
+                self.last_pose = Some(pose.clone());
+                self.position = point_to_array(&pose.position);
+                self.orientation = normalize_quaternion(&pose.quaternion);
+                return pose.clone();
+            }
+        };
+        let derivative_smoothing_factor = Self::smoothing_factor(self.config.derivative_cutoff, elapsed_seconds);
+
+        for (axis, measurement) in point_to_array(&pose.position).iter().enumerate() {
+            let derivative = (measurement - self.position[axis]) / elapsed_seconds;
+            self.position_derivative[axis] +=
+                derivative_smoothing_factor * (derivative - self.position_derivative[axis]);
+            let cutoff = self.cutoff(self.position_derivative[axis]);
+            self.position[axis] +=
+                Self::smoothing_factor(cutoff, elapsed_seconds) * (measurement - self.position[axis]);
+        }
+
+        let angular_speed = quaternion_angle(&self.orientation, &pose.quaternion) / elapsed_seconds;
+        self.angular_speed += derivative_smoothing_factor * (angular_speed - self.angular_speed);
+        let cutoff = self.cutoff(self.angular_speed);
+        self.orientation = slerp(
+            &self.orientation,
+            &pose.quaternion,
+            Self::smoothing_factor(cutoff, elapsed_seconds),
+        );
+
+        let filtered_pose = pose_from_quaternion(array_to_point(&self.position), &self.orientation, self.angle_unit);
+        self.last_timestamp_nanos = Some(timestamp_nanos);
+        self.last_pose = Some(filtered_pose.clone());
+
+        filtered_pose
+    }
+
+    fn reset(&mut self) {
+        self.last_timestamp_nanos = None;
+        self.last_pose = None;
+        self.position_derivative = [0.0; 3];
+        self.angular_speed = 0.0;
+    }
+}
+
+impl ConstantVelocityAxis {
+    fn new(position: f64, measurement_noise: f64) -> ConstantVelocityAxis {
+        ConstantVelocityAxis {
+            position,
+            velocity: 0.0,
+            covariance: [[measurement_noise, 0.0], [0.0, INITIAL_VELOCITY_VARIANCE]],
+        }
+    }
+
+    fn predict(&mut self, elapsed_seconds: f64, process_noise: f64) {
+        let dt = elapsed_seconds;
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        self.position += self.velocity * dt;
+        self.covariance = [
+            [
+                p00 + dt * (p10 + p01) + dt * dt * p11 + process_noise * dt.powi(3) / 3.0,
+                p01 + dt * p11 + process_noise * dt * dt / 2.0,
+            ],
+            [p10 + dt * p11 + process_noise * dt * dt / 2.0, p11 + process_noise * dt],
+        ];
+    }
+
+    fn update(&mut self, measurement: f64, measurement_noise: f64) {
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let innovation = measurement - self.position;
+        let innovation_variance = p00 + measurement_noise;
+        let (position_gain, velocity_gain) = (p00 / innovation_variance, p10 / innovation_variance);
+        self.position += position_gain * innovation;
+        self.velocity += velocity_gain * innovation;
+        self.covariance = [
+            [(1.0 - position_gain) * p00, (1.0 - position_gain) * p01],
+            [p10 - velocity_gain * p00, p11 - velocity_gain * p01],
+        ];
+    }
+}
+
+impl KalmanPoseFilter {
+    pub fn new(config: KalmanFilterConfig, angle_unit: AngleUnit) -> KalmanPoseFilter {
+        let orientation_axis = ConstantVelocityAxis::new(0.0, config.orientation_measurement_noise);
+
+        KalmanPoseFilter {
+            angle_unit,
+            last_timestamp_nanos: None,
+            last_pose: None,
+            position_axes: [ConstantVelocityAxis::new(0.0, config.position_measurement_noise); 3],
+            orientation: Default::default(),
+            orientation_axes: [orientation_axis; 3],
+            config,
+        }
+    }
+
+    /// Moves the orientation error held by the axes into the quaternion estimate.
+    fn apply_orientation_error(&mut self) {
+        let orientation_error = [
+            self.orientation_axes[0].position,
+            self.orientation_axes[1].position,
+            self.orientation_axes[2].position,
+        ];
+        self.orientation = normalize_quaternion(&multiply_quaternions(
+            &self.orientation,
+            &quaternion_from_rotation_vector(&orientation_error),
+        ));
+
+        for orientation_axis in self.orientation_axes.iter_mut() {
+            orientation_axis.position = 0.0;
+        }
+    }
+}
+
+impl PoseFilter for KalmanPoseFilter {
+    fn filter(&mut self, pose: &Pose, timestamp_nanos: i64) -> Pose {
+        let elapsed_seconds = match (
+            elapsed_seconds(self.last_timestamp_nanos, timestamp_nanos),
+            &self.last_pose,
+        ) {
+            (Some(elapsed_seconds), Some(_)) => elapsed_seconds,
+            (None, Some(last_pose)) => return last_pose.clone(),
+            (_, None) => {
+                let measured_position = point_to_array(&pose.position);
+                let orientation_axis = ConstantVelocityAxis::new(0.0, self.config.orientation_measurement_noise);
+
+                for (position_axis, measurement) in self.position_axes.iter_mut().zip(measured_position.iter()) {
+                    *position_axis = ConstantVelocityAxis::new(*measurement, self.config.position_measurement_noise);
+                }
+
+                self.orientation_axes = [orientation_axis; 3];
+                self.orientation = normalize_quaternion(&pose.quaternion);
+                self.last_timestamp_nanos = Some(timestamp_nanos);
+                self.last_pose = Some(pose.clone());
+                return pose.clone();
+            }
+        };
+
+        for (position_axis, measurement) in self.position_axes.iter_mut().zip(point_to_array(&pose.position).iter()) {
+            position_axis.predict(elapsed_seconds, self.config.position_process_noise);
+            position_axis.update(*measurement, self.config.position_measurement_noise);
+        }
+
+        for orientation_axis in self.orientation_axes.iter_mut() {
+            orientation_axis.predict(elapsed_seconds, self.config.orientation_process_noise);
+        }
+
+        self.apply_orientation_error();
+        let orientation_innovation = rotation_vector_from_quaternion(&multiply_quaternions(
+            &conjugate_quaternion(&self.orientation),
+            &pose.quaternion,
+        ));
+
+        for (orientation_axis, innovation) in self.orientation_axes.iter_mut().zip(orientation_innovation.iter()) {
+            orientation_axis.update(*innovation, self.config.orientation_measurement_noise);
+        }
+
+        self.apply_orientation_error();
+
+        let position = [
+            self.position_axes[0].position,
+            self.position_axes[1].position,
+            self.position_axes[2].position,
+        ];
+        let filtered_pose = pose_from_quaternion(array_to_point(&position), &self.orientation, self.angle_unit);
+        self.last_timestamp_nanos = Some(timestamp_nanos);
+        self.last_pose = Some(filtered_pose.clone());
+
+        filtered_pose
+    }
+
+    fn reset(&mut self) {
+        self.last_timestamp_nanos = None;
+        self.last_pose = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_NANOS: i64 = 8_333_333;
+
+    fn pose_at(position: [f64; 3], rotation_vector: [f64; 3]) -> Pose {
+        pose_from_quaternion(
+            array_to_point(&position),
+            &quaternion_from_rotation_vector(&rotation_vector),
+            AngleUnit::Degrees,
+        )
+    }
+
+    /// Deterministic jitter in `[-amplitude, amplitude]`.
+    fn jitter(frame: i64, amplitude: f64) -> f64 {
+        amplitude * ((frame * 7_919 % 1_000) as f64 / 500.0 - 1.0)
+    }
+
+    fn assert_filter_smooths_and_follows(pose_filter: &mut dyn PoseFilter) {
+        let mut max_position_error: f64 = 0.0;
+        let mut max_orientation_error: f64 = 0.0;
+
+        for frame in 0..240 {
+            let pose = pose_at(
+                [10.0 + jitter(frame, 0.5), -5.0, 100.0],
+                [0.3, jitter(frame + 1, 0.01), 0.0],
+            );
+            let filtered_pose = pose_filter.filter(&pose, frame * FRAME_NANOS);
+
+            if frame >= 120 {
+                max_position_error = max_position_error.max((filtered_pose.position.x - 10.0).abs());
+                max_orientation_error = max_orientation_error.max(quaternion_angle(
+                    &filtered_pose.quaternion,
+                    &quaternion_from_rotation_vector(&[0.3, 0.0, 0.0]),
+                ));
+            }
+        }
+
+        assert!(max_position_error < 0.25, "position error {}", max_position_error);
+        assert!(
+            max_orientation_error < 0.005,
+            "orientation error {}",
+            max_orientation_error
+        );
+
+        let mut filtered_pose = Pose::default();
+
+        for frame in 240..480 {
+            filtered_pose = pose_filter.filter(&pose_at([20.0, -5.0, 100.0], [0.0, 0.0, 1.0]), frame * FRAME_NANOS);
+        }
+
+        assert!((filtered_pose.position.x - 20.0).abs() < 0.01);
+        assert!(
+            quaternion_angle(
+                &filtered_pose.quaternion,
+                &quaternion_from_rotation_vector(&[0.0, 0.0, 1.0])
+            ) < 0.01
+        );
+
+        let repeated_pose = pose_filter.filter(&pose_at([0.0; 3], [0.0; 3]), 479 * FRAME_NANOS);
+        assert!((repeated_pose.position.x - filtered_pose.position.x).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn one_euro_filter_smooths_and_follows() {
+        let config = OneEuroFilterConfig {
+            min_cutoff: 0.5,
+            beta: 0.01,
+            derivative_cutoff: 1.0,
+        };
+        assert_filter_smooths_and_follows(&mut OneEuroPoseFilter::new(config, AngleUnit::Degrees));
+    }
+
+    #[test]
+    fn kalman_filter_smooths_and_follows() {
+        let config = KalmanFilterConfig {
+            orientation_process_noise: 0.1,
+            ..Default::default()
+        };
+        assert_filter_smooths_and_follows(&mut KalmanPoseFilter::new(config, AngleUnit::Degrees));
+    }
+
+    #[test]
+    fn kalman_filter_tracks_constant_rotation() {
+        let mut pose_filter = KalmanPoseFilter::new(Default::default(), AngleUnit::Radians);
+        let angular_velocity = 2.0;
+        let mut filtered_pose = Pose::default();
+
+        for frame in 0..240 {
+            let time_seconds = (frame * FRAME_NANOS) as f64 / NANOS_PER_SECOND;
+            let pose = pose_at([time_seconds, 0.0, 0.0], [0.0, angular_velocity * time_seconds, 0.0]);
+            filtered_pose = pose_filter.filter(&pose, frame * FRAME_NANOS);
+        }
+
+        let time_seconds = (239 * FRAME_NANOS) as f64 / NANOS_PER_SECOND;
+        let expected_orientation = quaternion_from_rotation_vector(&[0.0, angular_velocity * time_seconds, 0.0]);
+        assert!(quaternion_angle(&filtered_pose.quaternion, &expected_orientation) < 1e-3);
+        assert!((filtered_pose.position.x - time_seconds).abs() < 1e-3);
+    }
+
+    #[test]
+    fn create_pose_filter_from_config() {
+        let pose = pose_at([1.0, 2.0, 3.0], [0.1, 0.2, 0.3]);
+        let mut pose_filter = create_pose_filter(&PoseFilterConfig::None, AngleUnit::Degrees);
+        assert!((pose_filter.filter(&pose, 0).position.y - 2.0).abs() < f64::EPSILON);
+
+        let mut pose_filter = create_pose_filter(&PoseFilterConfig::Kalman(Default::default()), AngleUnit::Degrees);
+        assert!((pose_filter.filter(&pose, 0).position.z - 3.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::geometry::{normalize_quaternion, pose_from_quaternion, symmetric_eigen};
+use crate::types::{AngleUnit, Pose, PoseEstimate, PoseQuality, SafePoint3D, SafeQuaternion};
+use crate::world_frame::WorldFrame;
+use log::warn;
+
+/// Added to each station's squared RMS reprojection error, in px², so a near-perfect fit cannot take all the weight.
+const REPROJECTION_VARIANCE_FLOOR: f64 = 0.01;
+
+/// One base station's estimate of a tracker pose, already in the world frame.
+#[derive(Debug, Clone)]
+pub struct StationPose {
+    pub base_station_name: String,
+    pub pose_estimate: PoseEstimate,
+}
+
+/// A tracker's world-frame pose for one frame, with the normalized weight of every station that contributed.
+#[derive(Debug, Clone)]
+pub struct FusedPose {
+    pub pose: Pose,
+    pub station_weights: Vec<(String, f64)>,
+    pub confidence: f64,
+}
+
+/// Weight of a station's pose, inversely proportional to its squared RMS reprojection error and scaled by its
+/// confidence. Degenerate poses weigh nothing.
+pub fn station_weight(pose_quality: &PoseQuality) -> f64 {
+    if pose_quality.is_degenerate || !pose_quality.rms_reprojection_error.is_finite() {
+        return 0.0;
+    }
+
+    pose_quality.confidence.max(0.0) / (pose_quality.rms_reprojection_error.powi(2) + REPROJECTION_VARIANCE_FLOOR)
+}
+
+fn is_pose_finite(pose: &Pose) -> bool {
+    [
+        pose.position.x,
+        pose.position.y,
+        pose.position.z,
+        pose.quaternion.w,
+        pose.quaternion.x,
+        pose.quaternion.y,
+        pose.quaternion.z,
+    ]
+    .iter()
+    .all(|value| value.is_finite())
+}
+
+/// Fuses world-frame poses of the same tracker. An occluded station simply has no entry, and degenerate,
+/// zero-confidence or non-finite poses are ignored. Returns `None` when no station contributes.
+pub fn fuse_station_poses(station_poses: &[StationPose], angle_unit: AngleUnit) -> Option<FusedPose> {
+    let weighted_station_poses: Vec<(&StationPose, f64)> = station_poses
+        .iter()
+        .map(|station_pose| (station_pose, station_weight(&station_pose.pose_estimate.quality)))
+        .filter(|(station_pose, weight)| *weight > 0.0 && is_pose_finite(&station_pose.pose_estimate.pose))
+        .collect();
+    let total_weight: f64 = weighted_station_poses.iter().map(|(_, weight)| weight).sum();
+
+    if weighted_station_poses.is_empty() {
+        return None;
+    }
+
+    let mut position = [0.0; 3];
+    // Markley's average: the principal eigenvector of the weighted sum of q * q^T, which ignores quaternion signs
+    let mut quaternion_scatter = vec![vec![0.0; 4]; 4];
+    let mut miss_probability = 1.0;
+
+    for (station_pose, weight) in &weighted_station_poses {
+        let pose = &station_pose.pose_estimate.pose;
+        let weight = weight / total_weight;
+        let quaternion = normalize_quaternion(&pose.quaternion);
+        let quaternion = [quaternion.w, quaternion.x, quaternion.y, quaternion.z];
+
+        position[0] += weight * pose.position.x;
+        position[1] += weight * pose.position.y;
+        position[2] += weight * pose.position.z;
+
+        for (row, row_component) in quaternion_scatter.iter_mut().zip(quaternion.iter()) {
+            for (value, column_component) in row.iter_mut().zip(quaternion.iter()) {
+                *value += weight * row_component * column_component;
+            }
+        }
+
+        miss_probability *= 1.0 - station_pose.pose_estimate.quality.confidence.clamp(0.0, 1.0);
+    }
+
+    let (_, eigenvectors) = symmetric_eigen(&quaternion_scatter);
+    let quaternion = normalize_quaternion(&SafeQuaternion {
+        w: eigenvectors[0][0],
+        x: eigenvectors[0][1],
+        y: eigenvectors[0][2],
+        z: eigenvectors[0][3],
+    });
+
+    Some(FusedPose {
+        pose: pose_from_quaternion(
+            SafePoint3D::new(position[0], position[1], position[2]),
+            &quaternion,
+            angle_unit,
+        ),
+        station_weights: weighted_station_poses
+            .iter()
+            .map(|(station_pose, weight)| (station_pose.base_station_name.clone(), weight / total_weight))
+            .collect(),
+        confidence: 1.0 - miss_probability,
+    })
+}
+
+/// Moves each station's pose estimate of one tracker into the world frame, then fuses them. Estimates from stations
+/// missing in `world_frame` are skipped.
+pub fn fuse_pose_estimates(
+    world_frame: &WorldFrame,
+    pose_estimates: &[(&str, &PoseEstimate)],
+    angle_unit: AngleUnit,
+) -> Option<FusedPose> {
+    let station_poses: Vec<StationPose> = pose_estimates
+        .iter()
+        .filter_map(|(base_station_name, pose_estimate)| {
+            let pose = world_frame.pose_to_world(base_station_name, &pose_estimate.pose, angle_unit);
+
+            if pose.is_none() {
+                warn!("Ignoring pose from unknown base station \"{}\"", base_station_name);
+            }
+
+            pose.map(|pose| StationPose {
+                base_station_name: (*base_station_name).into(),
+                pose_estimate: PoseEstimate {
+                    pose,
+                    ..(*pose_estimate).clone()
+                },
+            })
+        })
+        .collect();
+
+    fuse_station_poses(&station_poses, angle_unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{quaternion_angle, quaternion_from_rotation_vector};
+
+    fn station_pose(
+        base_station_name: &str,
+        position: [f64; 3],
+        quaternion: SafeQuaternion,
+        rms_reprojection_error: f64,
+    ) -> StationPose {
+        StationPose {
+            base_station_name: base_station_name.into(),
+            pose_estimate: PoseEstimate {
+                pose: pose_from_quaternion(
+                    SafePoint3D::new(position[0], position[1], position[2]),
+                    &quaternion,
+                    AngleUnit::Degrees,
+                ),
+                inliers: (0..5).collect(),
+                quality: PoseQuality {
+                    reprojection_errors: vec![rms_reprojection_error; 5],
+                    rms_reprojection_error,
+                    condition_number: 10.0,
+                    is_degenerate: false,
+                    confidence: 0.9,
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn fusion_weights_stations_by_quality() {
+        let a_quaternion = quaternion_from_rotation_vector(&[0.0, 0.0, 0.1]);
+        // Negated, which is the same orientation in the other hemisphere
+        let b_quaternion = quaternion_from_rotation_vector(&[0.0, 0.0, 0.2]);
+        let b_quaternion = SafeQuaternion {
+            w: -b_quaternion.w,
+            x: -b_quaternion.x,
+            y: -b_quaternion.y,
+            z: -b_quaternion.z,
+        };
+        let station_poses = vec![
+            station_pose("StationA", [1.0, 0.0, 0.0], a_quaternion.clone(), 0.5),
+            station_pose("StationB", [2.0, 0.0, 0.0], b_quaternion, 1.5),
+        ];
+
+        let fused_pose = fuse_station_poses(&station_poses, AngleUnit::Degrees).unwrap();
+        assert_eq!(fused_pose.station_weights.len(), 2);
+        assert!(fused_pose.station_weights[0].1 > 0.8);
+        assert!(fused_pose.pose.position.x > 1.0 && fused_pose.pose.position.x < 1.2);
+        let fused_angle = quaternion_angle(&fused_pose.pose.quaternion, &quaternion_from_rotation_vector(&[0.0; 3]));
+        assert!(fused_angle > 0.1 && fused_angle < 0.12);
+        assert!(fused_pose.confidence > 0.98);
+
+        // StationB occluded, or reporting a degenerate pose
+        let fused_pose = fuse_station_poses(&station_poses[..1], AngleUnit::Degrees).unwrap();
+        assert!((fused_pose.pose.position.x - 1.0).abs() < 1e-9);
+        assert!(quaternion_angle(&fused_pose.pose.quaternion, &a_quaternion) < 1e-9);
+
+        let mut degenerate_station_poses = station_poses.clone();
+        degenerate_station_poses[1].pose_estimate.quality.is_degenerate = true;
+        let fused_pose = fuse_station_poses(&degenerate_station_poses, AngleUnit::Degrees).unwrap();
+        assert_eq!(fused_pose.station_weights, vec![("StationA".to_string(), 1.0)]);
+
+        let mut diverged_station_poses = station_poses.clone();
+        diverged_station_poses[1].pose_estimate.pose.quaternion.x = f64::NAN;
+        let fused_pose = fuse_station_poses(&diverged_station_poses, AngleUnit::Degrees).unwrap();
+        assert_eq!(fused_pose.station_weights, vec![("StationA".to_string(), 1.0)]);
+        diverged_station_poses[0].pose_estimate.pose.position.y = f64::INFINITY;
+        assert!(fuse_station_poses(&diverged_station_poses, AngleUnit::Degrees).is_none());
+
+        assert!(fuse_station_poses(&[], AngleUnit::Degrees).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::geometry::{
+    conjugate_quaternion, multiply_quaternions, normalize_quaternion, pose_from_quaternion,
+    quaternion_from_rotation_vector, rotation_vector_from_quaternion,
+};
+use crate::pose_filter::NANOS_PER_SECOND;
+use crate::types::{AngleUnit, Pose, PosePredictorConfig, SafePoint3D};
+
+const MILLISECONDS_PER_SECOND: f64 = 1_000.0;
+
+/// Extrapolates a tracker's latest pose to a requested timestamp, typically the image generator's render time, to
+/// hide the latency between LED capture and rendering. Velocities are estimated from the stream of poses given to
+/// `update`; the angular velocity is expressed in the pose's reference frame, in radians per second.
+#[derive(Debug, Clone)]
+pub struct PosePredictor {
+    pose_predictor_config: PosePredictorConfig,
+    angle_unit: AngleUnit,
+    latest_timestamp_nanos: Option<i64>,
+    latest_pose: Option<Pose>,
+    linear_velocity: Option<[f64; 3]>,
+    angular_velocity: Option<[f64; 3]>,
+}
+
+impl PosePredictor {
+    pub fn new(pose_predictor_config: PosePredictorConfig, angle_unit: AngleUnit) -> PosePredictor {
+        PosePredictor {
+            pose_predictor_config,
+            angle_unit,
+            latest_timestamp_nanos: None,
+            latest_pose: None,
+            linear_velocity: None,
+            angular_velocity: None,
+        }
+    }
+
+    pub fn latest_timestamp_nanos(&self) -> Option<i64> {
+        self.latest_timestamp_nanos
+    }
+
+    /// Linear velocity in the pose convention's length unit per second, zero until two poses were seen.
+    pub fn linear_velocity(&self) -> [f64; 3] {
+        self.linear_velocity.unwrap_or_default()
+    }
+
+    /// Angular velocity as a rotation vector per second, zero until two poses were seen.
+    pub fn angular_velocity(&self) -> [f64; 3] {
+        self.angular_velocity.unwrap_or_default()
+    }
+
+    /// Forgets the pose history, e.g. after tracking was lost.
+    pub fn reset(&mut self) {
+        self.latest_timestamp_nanos = None;
+        self.latest_pose = None;
+        self.linear_velocity = None;
+        self.angular_velocity = None;
+    }
+
+    /// Poses that are not newer than the latest one are ignored.
+    pub fn update(&mut self, pose: &Pose, timestamp_nanos: i64) {
+        if let (Some(latest_timestamp_nanos), Some(latest_pose)) = (self.latest_timestamp_nanos, &self.latest_pose) {
+            if timestamp_nanos <= latest_timestamp_nanos {
+                return;
+            }
+
+            let elapsed_seconds = (timestamp_nanos - latest_timestamp_nanos) as f64 / NANOS_PER_SECOND;
+            let linear_velocity = [
+                (pose.position.x - latest_pose.position.x) / elapsed_seconds,
+                (pose.position.y - latest_pose.position.y) / elapsed_seconds,
+                (pose.position.z - latest_pose.position.z) / elapsed_seconds,
+            ];
+            let rotation = rotation_vector_from_quaternion(&multiply_quaternions(
+                &pose.quaternion,
+                &conjugate_quaternion(&latest_pose.quaternion),
+            ));
+            let angular_velocity = [
+                rotation[0] / elapsed_seconds,
+                rotation[1] / elapsed_seconds,
+                rotation[2] / elapsed_seconds,
+            ];
+            let smoothing_factor = self.smoothing_factor(elapsed_seconds);
+
+            self.linear_velocity = Some(Self::smooth(self.linear_velocity, linear_velocity, smoothing_factor));
+            self.angular_velocity = Some(Self::smooth(self.angular_velocity, angular_velocity, smoothing_factor));
+        }
+
+        self.latest_timestamp_nanos = Some(timestamp_nanos);
+        self.latest_pose = Some(pose.clone());
+    }
+
+    /// Pose extrapolated to `timestamp_nanos`, or `None` before the first pose. Timestamps before the latest pose
+    /// return it unchanged, and extrapolation stops at `MaxPredictionInterval` past it.
+    pub fn predict(&self, timestamp_nanos: i64) -> Option<Pose> {
+        let latest_timestamp_nanos = self.latest_timestamp_nanos?;
+        let latest_pose = self.latest_pose.as_ref()?;
+        let max_prediction_seconds = self.pose_predictor_config.max_prediction_interval / MILLISECONDS_PER_SECOND;
+        let prediction_seconds = ((timestamp_nanos - latest_timestamp_nanos) as f64 / NANOS_PER_SECOND)
+            .max(0.0)
+            .min(max_prediction_seconds);
+        let linear_velocity = self.linear_velocity();
+        let angular_velocity = self.angular_velocity();
+
+        let position = SafePoint3D::new(
+            latest_pose.position.x + linear_velocity[0] * prediction_seconds,
+            latest_pose.position.y + linear_velocity[1] * prediction_seconds,
+            latest_pose.position.z + linear_velocity[2] * prediction_seconds,
+        );
+        let rotation = quaternion_from_rotation_vector(&[
+            angular_velocity[0] * prediction_seconds,
+            angular_velocity[1] * prediction_seconds,
+            angular_velocity[2] * prediction_seconds,
+        ]);
+        let quaternion = normalize_quaternion(&multiply_quaternions(&rotation, &latest_pose.quaternion));
+
+        Some(pose_from_quaternion(position, &quaternion, self.angle_unit))
+    }
+
+    fn smoothing_factor(&self, elapsed_seconds: f64) -> f64 {
+        let time_constant = self.pose_predictor_config.velocity_time_constant / MILLISECONDS_PER_SECOND;
+        elapsed_seconds / (elapsed_seconds + time_constant)
+    }
+
+    fn smooth(previous: Option<[f64; 3]>, current: [f64; 3], smoothing_factor: f64) -> [f64; 3] {
+        match previous {
+            Some(previous) => [
+                previous[0] + smoothing_factor * (current[0] - previous[0]),
+                previous[1] + smoothing_factor * (current[1] - previous[1]),
+                previous[2] + smoothing_factor * (current[2] - previous[2]),
+            ],
+            None => current,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::quaternion_angle;
+
+    const FRAME_NANOS: i64 = 10_000_000;
+
+    fn moving_pose(time_seconds: f64) -> Pose {
+        pose_from_quaternion(
+            SafePoint3D::new(1.0 + 2.0 * time_seconds, -0.5 * time_seconds, 3.0),
+            &quaternion_from_rotation_vector(&[0.0, 0.0, 1.5 * time_seconds]),
+            AngleUnit::Radians,
+        )
+    }
+
+    #[test]
+    fn pose_predictor_extrapolates_constant_motion() {
+        let mut pose_predictor = PosePredictor::new(Default::default(), AngleUnit::Radians);
+        assert!(pose_predictor.predict(0).is_none());
+
+        for frame in 0..50 {
+            let timestamp_nanos = frame * FRAME_NANOS;
+            pose_predictor.update(&moving_pose(timestamp_nanos as f64 / NANOS_PER_SECOND), timestamp_nanos);
+        }
+
+        let latest_timestamp_nanos = pose_predictor.latest_timestamp_nanos().unwrap();
+        let render_timestamp_nanos = latest_timestamp_nanos + 40_000_000;
+        let predicted_pose = pose_predictor.predict(render_timestamp_nanos).unwrap();
+        let expected_pose = moving_pose(render_timestamp_nanos as f64 / NANOS_PER_SECOND);
+        assert!((predicted_pose.position.x - expected_pose.position.x).abs() < 1e-9);
+        assert!((predicted_pose.position.y - expected_pose.position.y).abs() < 1e-9);
+        assert!(quaternion_angle(&predicted_pose.quaternion, &expected_pose.quaternion) < 1e-9);
+        assert!((pose_predictor.angular_velocity()[2] - 1.5).abs() < 1e-9);
+
+        // Older or far-future render times are clamped to the latest pose and to MaxPredictionInterval
+        let latest_pose = pose_predictor.predict(0).unwrap();
+        assert!((latest_pose.position.x - moving_pose(0.49).position.x).abs() < 1e-9);
+        let clamped_pose = pose_predictor.predict(latest_timestamp_nanos + 1_000_000_000).unwrap();
+        assert!((clamped_pose.position.x - moving_pose(0.59).position.x).abs() < 1e-9);
+
+        pose_predictor.reset();
+        assert!(pose_predictor.predict(render_timestamp_nanos).is_none());
+    }
+}
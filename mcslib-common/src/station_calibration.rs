@@ -0,0 +1,354 @@
+use crate::config::{invalid_config_error, ConfigValidation};
+use crate::geometry::{
+    centroid, distance, euler_angles_from_rotation_matrix, rigid_transform_between, rotate,
+    rotation_matrix_from_euler_angles, subtract, symmetric_eigen,
+};
+use crate::types::{AngleUnit, BaseStations, BaseStationsConfig, CalibrationObservation, RotationMatrix, SafePoint3D};
+use std::collections::BTreeMap;
+use std::io::Result as IOResult;
+
+/// Shared placements needed to align a station, the minimum for Horn's method.
+pub const MIN_SHARED_PLACEMENTS: usize = 3;
+/// Ratio of the second to the largest spread of the shared placements below which they count as collinear.
+const COLLINEARITY_TOLERANCE: f64 = 1e-6;
+
+/// How well a calibrated station agrees with the others: distances between its view of each placement and the
+/// placement's world position averaged over every station, in the pose convention's length unit.
+#[derive(Debug, Clone)]
+pub struct StationResidual {
+    pub station_name: String,
+    pub observation_count: usize,
+    pub rms_residual: f64,
+    pub max_residual: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BaseStationCalibration {
+    pub base_stations_config: BaseStationsConfig,
+    pub residuals: Vec<StationResidual>,
+}
+
+#[derive(Debug, Clone)]
+struct StationExtrinsics {
+    rotation_matrix: RotationMatrix,
+    position: SafePoint3D,
+}
+
+impl StationExtrinsics {
+    fn to_world(&self, point: &SafePoint3D) -> SafePoint3D {
+        let rotated_point = rotate(&self.rotation_matrix, point);
+
+        SafePoint3D::new(
+            rotated_point.x + self.position.x,
+            rotated_point.y + self.position.y,
+            rotated_point.z + self.position.z,
+        )
+    }
+}
+
+/// World position of every placement seen by a calibrated station, averaged over those stations.
+fn placement_world_positions(
+    observations: &[CalibrationObservation],
+    station_extrinsics: &BTreeMap<String, StationExtrinsics>,
+) -> BTreeMap<usize, SafePoint3D> {
+    let mut placement_points: BTreeMap<usize, Vec<SafePoint3D>> = BTreeMap::new();
+
+    for observation in observations {
+        if let Some(extrinsics) = station_extrinsics.get(&observation.station_name) {
+            placement_points
+                .entry(observation.placement)
+                .or_default()
+                .push(extrinsics.to_world(&observation.position));
+        }
+    }
+
+    placement_points
+        .into_iter()
+        .map(|(placement, points)| (placement, centroid(&points)))
+        .collect()
+}
+
+fn is_collinear(points: &[SafePoint3D]) -> bool {
+    let center = centroid(points);
+    let mut covariance = vec![vec![0.0; 3]; 3];
+
+    for point in points {
+        let offset = subtract(point, &center);
+        let offset = [offset.x, offset.y, offset.z];
+
+        for (row, row_component) in covariance.iter_mut().zip(offset.iter()) {
+            for (value, column_component) in row.iter_mut().zip(offset.iter()) {
+                *value += row_component * column_component;
+            }
+        }
+    }
+
+    let (eigenvalues, _) = symmetric_eigen(&covariance);
+    eigenvalues[1] <= eigenvalues[0] * COLLINEARITY_TOLERANCE
+}
+
+/// Solves every base station's `Position` and `Rotation` from observations of one calibration tracker moved through
+/// several placements. The origin station keeps its configured extrinsics and defines the world frame; any other
+/// station is aligned once it shares at least `MIN_SHARED_PLACEMENTS` non-collinear placements with stations
+/// already calibrated, so stations that never see the origin's placements can be chained through their neighbours.
+pub fn calibrate_base_stations(
+    base_stations_config: &BaseStationsConfig,
+    origin_station_name: &str,
+    observations: &[CalibrationObservation],
+) -> IOResult<BaseStationCalibration> {
+    let origin_station = base_stations_config
+        .0
+        .iter()
+        .find(|base_station| base_station.station_name == origin_station_name)
+        .ok_or_else(|| invalid_config_error(format!("Unknown origin base station \"{}\"", origin_station_name)))?;
+
+    if let Some(observation) = observations.iter().find(|observation| {
+        !base_stations_config
+            .0
+            .iter()
+            .any(|base_station| base_station.station_name == observation.station_name)
+    }) {
+        return Err(invalid_config_error(format!(
+            "Calibration observation from unknown base station \"{}\"",
+            observation.station_name
+        )));
+    }
+
+    for observation in observations {
+        observation.validate()?;
+    }
+
+    let mut station_extrinsics = BTreeMap::new();
+    station_extrinsics.insert(
+        origin_station_name.to_string(),
+        StationExtrinsics {
+            rotation_matrix: rotation_matrix_from_euler_angles(&origin_station.rotation, AngleUnit::Degrees),
+            position: origin_station.position.clone(),
+        },
+    );
+
+    loop {
+        let world_positions = placement_world_positions(observations, &station_extrinsics);
+        // Align the station sharing the most placements first, its fit is the best constrained
+        let next_station = base_stations_config
+            .0
+            .iter()
+            .filter(|base_station| !station_extrinsics.contains_key(&base_station.station_name))
+            .map(|base_station| {
+                let (station_points, world_points): (Vec<SafePoint3D>, Vec<SafePoint3D>) = observations
+                    .iter()
+                    .filter(|observation| observation.station_name == base_station.station_name)
+                    .filter_map(|observation| {
+                        world_positions
+                            .get(&observation.placement)
+                            .map(|world_position| (observation.position.clone(), world_position.clone()))
+                    })
+                    .unzip();
+                (base_station, station_points, world_points)
+            })
+            .filter(|(_, station_points, _)| {
+                station_points.len() >= MIN_SHARED_PLACEMENTS && !is_collinear(station_points)
+            })
+            .max_by_key(|(_, station_points, _)| station_points.len());
+
+        match next_station {
+            Some((base_station, station_points, world_points)) => {
+                let (rotation_matrix, position) = rigid_transform_between(&station_points, &world_points);
+                station_extrinsics.insert(
+                    base_station.station_name.clone(),
+                    StationExtrinsics {
+                        rotation_matrix,
+                        position,
+                    },
+                );
+            }
+            None => break,
+        }
+    }
+
+    if let Some(base_station) = base_stations_config
+        .0
+        .iter()
+        .find(|base_station| !station_extrinsics.contains_key(&base_station.station_name))
+    {
+        return Err(invalid_config_error(format!(
+            "Base station \"{}\" does not share {} non-collinear placements with the calibrated stations",
+            base_station.station_name, MIN_SHARED_PLACEMENTS
+        )));
+    }
+
+    let world_positions = placement_world_positions(observations, &station_extrinsics);
+    let residuals = base_stations_config
+        .0
+        .iter()
+        .map(|base_station| {
+            let extrinsics = &station_extrinsics[&base_station.station_name];
+            let distances: Vec<f64> = observations
+                .iter()
+                .filter(|observation| observation.station_name == base_station.station_name)
+                .map(|observation| {
+                    distance(
+                        &extrinsics.to_world(&observation.position),
+                        &world_positions[&observation.placement],
+                    )
+                })
+                .collect();
+            let mean_squared_residual =
+                distances.iter().map(|distance| distance * distance).sum::<f64>() / distances.len().max(1) as f64;
+
+            StationResidual {
+                station_name: base_station.station_name.clone(),
+                observation_count: distances.len(),
+                rms_residual: mean_squared_residual.sqrt(),
+                max_residual: distances.iter().cloned().fold(0.0, f64::max),
+            }
+        })
+        .collect();
+    let base_stations_config = BaseStationsConfig(
+        base_stations_config
+            .0
+            .iter()
+            .map(|base_station| {
+                let extrinsics = &station_extrinsics[&base_station.station_name];
+
+                BaseStations {
+                    station_name: base_station.station_name.clone(),
+                    position: extrinsics.position.clone(),
+                    rotation: euler_angles_from_rotation_matrix(&extrinsics.rotation_matrix, AngleUnit::Degrees),
+                }
+            })
+            .collect(),
+    );
+
+    Ok(BaseStationCalibration {
+        base_stations_config,
+        residuals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::transpose;
+    use crate::types::{CalibrationObservations, JsonSerializable, SafeEulerAngles};
+    use crate::world_frame::WorldFrame;
+
+    fn base_station(station_name: &str, position: [f64; 3], rotation: [f64; 3]) -> BaseStations {
+        BaseStations {
+            station_name: station_name.into(),
+            position: SafePoint3D::new(position[0], position[1], position[2]),
+            rotation: SafeEulerAngles {
+                roll: rotation[0],
+                pitch: rotation[1],
+                yaw: rotation[2],
+            },
+        }
+    }
+
+    #[test]
+    fn base_stations_are_calibrated_from_placements() {
+        let true_base_stations_config = BaseStationsConfig(vec![
+            base_station("StationA", [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]),
+            base_station("StationB", [4.0, 0.0, 0.0], [0.0, 0.0, 180.0]),
+            base_station("StationC", [2.0, 3.0, 1.0], [10.0, -30.0, -90.0]),
+        ]);
+        let placements = [
+            [1.0, 0.5, 0.0],
+            [2.0, -0.5, 0.3],
+            [3.0, 0.0, 1.0],
+            [2.0, 1.0, -0.5],
+            [1.5, 1.5, 0.5],
+        ];
+        // StationC misses the first placement and StationA the last, which only reaches the world through StationB
+        let mut observations = vec![];
+
+        for (placement, world_position) in placements.iter().enumerate() {
+            for base_station in &true_base_stations_config.0 {
+                if (base_station.station_name == "StationC" && placement == 0)
+                    || (base_station.station_name == "StationA" && placement == 4)
+                {
+                    continue;
+                }
+
+                let inverse_rotation = transpose(&rotation_matrix_from_euler_angles(
+                    &base_station.rotation,
+                    AngleUnit::Degrees,
+                ));
+                let world_position = SafePoint3D::new(world_position[0], world_position[1], world_position[2]);
+                let noise = 0.001 * ((placement * 3 + base_station.station_name.len()) % 5) as f64;
+                let position = rotate(&inverse_rotation, &subtract(&world_position, &base_station.position));
+
+                observations.push(CalibrationObservation {
+                    placement,
+                    station_name: base_station.station_name.clone(),
+                    position: SafePoint3D::new(position.x + noise, position.y, position.z),
+                });
+            }
+        }
+
+        let calibration = calibrate_base_stations(&BaseStationsConfig::default(), "StationA", &observations);
+        assert!(calibration.is_err());
+
+        let mut base_stations_config = BaseStationsConfig::default();
+        base_stations_config
+            .0
+            .push(base_station("StationC", [0.0; 3], [0.0; 3]));
+        let calibration = calibrate_base_stations(&base_stations_config, "StationA", &observations).unwrap();
+        let world_frame = WorldFrame::new(&calibration.base_stations_config);
+        let true_world_frame = WorldFrame::new(&true_base_stations_config);
+
+        for base_station in &true_base_stations_config.0 {
+            let point = SafePoint3D::new(0.3, -0.2, 0.7);
+            let calibrated_point = world_frame
+                .base_station_transform(&base_station.station_name)
+                .unwrap()
+                .point_to_world(&point);
+            let true_point = true_world_frame
+                .base_station_transform(&base_station.station_name)
+                .unwrap()
+                .point_to_world(&point);
+            assert!(distance(&calibrated_point, &true_point) < 0.02);
+        }
+
+        assert_eq!(calibration.residuals.len(), 3);
+        assert!(calibration
+            .residuals
+            .iter()
+            .all(|residual| residual.observation_count >= 4 && residual.rms_residual < 0.01));
+
+        let sparse_observations: Vec<CalibrationObservation> = observations
+            .iter()
+            .filter(|observation| observation.station_name != "StationC" || observation.placement < 3)
+            .cloned()
+            .collect();
+        assert!(calibrate_base_stations(&base_stations_config, "StationA", &sparse_observations).is_err());
+    }
+
+    #[test]
+    fn malformed_observations_are_rejected() {
+        assert!(CalibrationObservations::from_json(r#"[{"Placement": 0, "StationName": "StationA"}]"#).is_err());
+        assert!(CalibrationObservations::from_json(
+            r#"[{"Placement": -1, "StationName": "StationA", "Position": {}}]"#
+        )
+        .is_err());
+
+        let mut observations = vec![];
+
+        for (placement, position) in [[1e200, 0.0, 0.0], [0.0, 1e200, 0.0], [0.0, 0.0, 1e200]]
+            .iter()
+            .enumerate()
+        {
+            for station_name in &["StationA", "StationB"] {
+                observations.push(CalibrationObservation {
+                    placement,
+                    station_name: (*station_name).into(),
+                    position: SafePoint3D::new(position[0], position[1], position[2]),
+                });
+            }
+        }
+
+        let json = CalibrationObservations(observations).to_json();
+        let observations = CalibrationObservations::from_json(&json).unwrap();
+        assert!(calibrate_base_stations(&BaseStationsConfig::default(), "StationA", &observations.0).is_err());
+    }
+}
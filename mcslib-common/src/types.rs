@@ -1,3 +1,4 @@
+use crate::geometry::IDENTITY_MATRIX;
 use crate::serialport::{
     DataBits as SPDataBits, FlowControl as SPFlowControl, Parity as SPParity, SerialPortSettings as SPSettings,
     StopBits as SPStopBits,
@@ -12,6 +13,8 @@ use std::time::Duration;
 pub type SerialPortName = String;
 
 const JSON_MAPPING_ERROR_MESSAGE: &str = "Possible JSON mapping failure!";
+const LEGACY_STATION_A_NAME: &str = "StationA";
+const LEGACY_STATION_B_NAME: &str = "StationB";
 
 pub trait JsonSerializable<'a, T = Self>
 where
@@ -124,20 +127,304 @@ pub struct SafeEulerAngles {
     pub yaw: f64,
 }
 
+/// Unit quaternion `w + xi + yj + zk`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SafeQuaternion {
+    #[serde(rename = "W")]
+    pub w: f64,
+    #[serde(rename = "X")]
+    pub x: f64,
+    #[serde(rename = "Y")]
+    pub y: f64,
+    #[serde(rename = "Z")]
+    pub z: f64,
+}
+
+/// Row-major 3x3 rotation matrix.
+pub type RotationMatrix = [[f64; 3]; 3];
+
+/// Axis convention of a `Pose`. Every convention is right-handed and derived from the OpenCV camera frame.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AxisConvention {
+    /// OpenCV camera frame: X right, Y down, Z forward.
+    OpenCV,
+    /// X forward, Y right, Z down, i.e. camera Z, X, Y. This is the axis order `compute_pose` used to emit.
+    ForwardRightDown,
+    /// OpenGL camera frame: X right, Y up, Z backward.
+    OpenGL,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AngleUnit {
+    Degrees,
+    Radians,
+}
+
+/// How a `Pose` is expressed. `LengthScale` multiplies the marker constellation unit, e.g. `0.001` for a
+/// constellation in millimetres and a position in metres.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PoseConvention {
+    #[serde(rename = "AxisConvention")]
+    pub axis_convention: AxisConvention,
+    #[serde(rename = "AngleUnit")]
+    pub angle_unit: AngleUnit,
+    #[serde(rename = "LengthScale")]
+    pub length_scale: f64,
+}
+
+/// Pose of a tracker in the frame given by `PoseConvention`. The Euler angles are intrinsic Z-Y-X, i.e.
+/// `R = Rz(yaw) * Ry(pitch) * Rx(roll)`, in the convention's angle unit.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Pose {
+    #[serde(rename = "Position")]
+    pub position: SafePoint3D,
+    #[serde(rename = "Orientation")]
+    pub orientation: SafeEulerAngles,
+    #[serde(rename = "Quaternion")]
+    pub quaternion: SafeQuaternion,
+    #[serde(rename = "RotationMatrix")]
+    pub rotation_matrix: RotationMatrix,
+}
+
+/// How well a pose fits its image points. Reprojection errors are in pixels, one per image point; the RMS only covers
+/// inliers. The condition number is that of the pose Jacobian, with translation measured in constellation sizes,
+/// so large values mean the image points barely constrain the pose whatever the length unit. The confidence is
+/// within `[0, 1]` and zero for degenerate poses.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PoseQuality {
+    #[serde(rename = "ReprojectionErrors")]
+    pub reprojection_errors: Vec<f64>,
+    #[serde(rename = "RmsReprojectionError")]
+    pub rms_reprojection_error: f64,
+    #[serde(rename = "ConditionNumber")]
+    pub condition_number: f64,
+    #[serde(rename = "IsDegenerate")]
+    pub is_degenerate: bool,
+    #[serde(rename = "Confidence")]
+    pub confidence: f64,
+}
+
+/// A solved pose, the indices of the image points that support it and its fit quality.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PoseEstimate {
+    #[serde(rename = "Pose")]
+    pub pose: Pose,
+    #[serde(rename = "Inliers")]
+    pub inliers: Vec<usize>,
+    #[serde(rename = "Quality")]
+    pub quality: PoseQuality,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct BaseStations {
+    #[serde(rename = "StationName")]
+    pub station_name: String,
     #[serde(rename = "Position")]
     pub position: SafePoint3D,
     #[serde(rename = "Rotation")]
     pub rotation: SafeEulerAngles,
 }
 
+/// Base stations by name. The former two-station layout `{"StationA": {..}, "StationB": {..}}` is still read, as
+/// stations named after its keys, and is written back as a list.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(from = "BaseStationsConfigFormat")]
+pub struct BaseStationsConfig(pub Vec<BaseStations>);
+
+/// Position of the calibration tracker at one of its placements, as solved by one base station in that station's
+/// frame.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CalibrationObservation {
+    #[serde(rename = "Placement")]
+    pub placement: usize,
+    #[serde(rename = "StationName")]
+    pub station_name: String,
+    #[serde(rename = "Position")]
+    pub position: SafePoint3D,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CalibrationObservations(pub Vec<CalibrationObservation>);
+
+#[derive(Deserialize)]
+struct LegacyBaseStation {
+    #[serde(rename = "Position")]
+    position: SafePoint3D,
+    #[serde(rename = "Rotation")]
+    rotation: SafeEulerAngles,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BaseStationsConfigFormat {
+    Named(Vec<BaseStations>),
+    TwoStations {
+        #[serde(rename = "StationA")]
+        station_a: LegacyBaseStation,
+        #[serde(rename = "StationB")]
+        station_b: LegacyBaseStation,
+    },
+}
+
+/// Lens distortion models. `RadialTangential` reads the distortion coefficients in OpenCV order
+/// `(k1, k2, p1, p2, k3)`; `Fisheye` reads the first four as the equidistant model's `(k1, k2, k3, k4)` and ignores
+/// the fifth.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistortionModel {
+    RadialTangential,
+    Fisheye,
+}
+
+/// Where image points are undistorted. `InSolver` hands the distortion coefficients to `solvePnP` with raw pixel
+/// points; `PreStep` expects points already undistorted, so one undistortion can feed several solves. The fisheye
+/// model is only supported as a pre-step.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UndistortionMode {
+    InSolver,
+    PreStep,
+}
+
+/// Pinhole intrinsics of a tracker sensor, in pixels, with the distortion coefficients of `DistortionModel`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CameraIntrinsics {
+    #[serde(rename = "Fx")]
+    pub fx: f64,
+    #[serde(rename = "Fy")]
+    pub fy: f64,
+    #[serde(rename = "Cx")]
+    pub cx: f64,
+    #[serde(rename = "Cy")]
+    pub cy: f64,
+    #[serde(rename = "DistortionCoefficients")]
+    pub distortion_coefficients: [f64; 5],
+    #[serde(rename = "DistortionModel", default)]
+    pub distortion_model: DistortionModel,
+}
+
+/// Reprojection error of one calibration image, in pixels.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ImageResidual {
+    #[serde(rename = "ImageName")]
+    pub image_name: String,
+    #[serde(rename = "RmsReprojectionError")]
+    pub rms_reprojection_error: f64,
+}
+
+/// Result of an intrinsic calibration, loadable by the pose solver through a tracker's `CameraCalibrationPath`.
+/// Images where the calibration pattern was not found are listed in `SkippedImages`, and files that could not be read
+/// as images in `UnreadableImages`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CameraCalibration {
+    #[serde(rename = "CameraIntrinsics")]
+    pub camera_intrinsics: CameraIntrinsics,
+    #[serde(rename = "ImageWidth")]
+    pub image_width: u32,
+    #[serde(rename = "ImageHeight")]
+    pub image_height: u32,
+    #[serde(rename = "RmsReprojectionError")]
+    pub rms_reprojection_error: f64,
+    #[serde(rename = "ImageResiduals")]
+    pub image_residuals: Vec<ImageResidual>,
+    #[serde(rename = "SkippedImages")]
+    pub skipped_images: Vec<String>,
+    #[serde(rename = "UnreadableImages", default)]
+    pub unreadable_images: Vec<String>,
+}
+
+/// OpenCV `solvePnP` flavours. `P3P` and `AP3P` need exactly four points without RANSAC; `IPPESquare` needs the
+/// four corners of a square centred on the origin of the Z=0 plane, ordered (-h, h), (h, h), (h, -h), (-h, -h).
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PnPMethod {
+    Iterative,
+    EPnP,
+    P3P,
+    AP3P,
+    IPPESquare,
+}
+
+/// `solvePnPRansac` parameters. `ReprojectionError` is the inlier threshold in pixels.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RansacConfig {
+    #[serde(rename = "IterationsCount")]
+    pub iterations_count: u32,
+    #[serde(rename = "ReprojectionError")]
+    pub reprojection_error: f64,
+    #[serde(rename = "Confidence")]
+    pub confidence: f64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PoseSolverConfig {
+    #[serde(rename = "PnPMethod")]
+    pub pnp_method: PnPMethod,
+    #[serde(rename = "Ransac")]
+    pub ransac: Option<RansacConfig>,
+    /// RMS reprojection error in pixels above which a tracked pose is dropped for a full solve.
+    #[serde(
+        rename = "TrackingReprojectionThreshold",
+        default = "default_tracking_reprojection_threshold"
+    )]
+    pub tracking_reprojection_threshold: f64,
+    #[serde(rename = "Undistortion", default)]
+    pub undistortion: UndistortionMode,
+}
+
+/// One Euro filter parameters: `MinCutoff` and `DerivativeCutoff` in Hz, `Beta` scales the cutoff with speed.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct OneEuroFilterConfig {
+    #[serde(rename = "MinCutoff")]
+    pub min_cutoff: f64,
+    #[serde(rename = "Beta")]
+    pub beta: f64,
+    #[serde(rename = "DerivativeCutoff")]
+    pub derivative_cutoff: f64,
+}
+
+/// Constant-velocity Kalman filter parameters. Process noises are acceleration spectral densities, measurement
+/// noises are variances, in the pose convention's length unit and radians.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct KalmanFilterConfig {
+    #[serde(rename = "PositionProcessNoise")]
+    pub position_process_noise: f64,
+    #[serde(rename = "PositionMeasurementNoise")]
+    pub position_measurement_noise: f64,
+    #[serde(rename = "OrientationProcessNoise")]
+    pub orientation_process_noise: f64,
+    #[serde(rename = "OrientationMeasurementNoise")]
+    pub orientation_measurement_noise: f64,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct BaseStationsConfig {
-    #[serde(rename = "StationA")]
-    pub station_a: BaseStations,
-    #[serde(rename = "StationB")]
-    pub station_b: BaseStations,
+pub enum PoseFilterConfig {
+    None,
+    OneEuro(OneEuroFilterConfig),
+    Kalman(KalmanFilterConfig),
+}
+
+/// Host-side LED detection for trackers that send full grayscale frames instead of image points. Pixels brighter
+/// than `Threshold` (0-255) form blobs; `MinArea` and `MaxArea` bound their pixel count and `MinCircularity` their
+/// minor to major axis ratio, 1 for a round blob.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BlobDetectorConfig {
+    #[serde(rename = "Threshold")]
+    pub threshold: f64,
+    #[serde(rename = "MinArea")]
+    pub min_area: f64,
+    #[serde(rename = "MaxArea")]
+    pub max_area: f64,
+    #[serde(rename = "MinCircularity")]
+    pub min_circularity: f64,
+}
+
+/// Pose extrapolation towards the render time, both intervals in milliseconds. Predictions are never extrapolated
+/// further than `MaxPredictionInterval` past the latest pose, and velocities are low-passed with a
+/// `VelocityTimeConstant` time constant (0 uses the raw difference of the last two poses).
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PosePredictorConfig {
+    #[serde(rename = "MaxPredictionInterval")]
+    pub max_prediction_interval: f64,
+    #[serde(rename = "VelocityTimeConstant")]
+    pub velocity_time_constant: f64,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -146,6 +433,21 @@ pub struct TrackerEndpoint {
     pub tracker_name: String,
     #[serde(rename = "TrackerCommunication")]
     pub tracker_communication: TrackerCommunication,
+    #[serde(rename = "CameraIntrinsics", default)]
+    pub camera_intrinsics: CameraIntrinsics,
+    /// Overrides `CameraIntrinsics` with a `CameraCalibration` file when set.
+    #[serde(rename = "CameraCalibrationPath", default)]
+    pub camera_calibration_path: Option<String>,
+    #[serde(rename = "MarkerConstellation", default = "default_marker_constellation")]
+    pub marker_constellation: Vec<SafePoint3D>,
+    #[serde(rename = "PoseSolverConfig", default)]
+    pub pose_solver_config: PoseSolverConfig,
+    #[serde(rename = "PoseFilterConfig", default)]
+    pub pose_filter_config: PoseFilterConfig,
+    #[serde(rename = "PosePredictorConfig", default)]
+    pub pose_predictor_config: PosePredictorConfig,
+    #[serde(rename = "BlobDetectorConfig", default)]
+    pub blob_detector_config: Option<BlobDetectorConfig>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -163,7 +465,7 @@ pub struct TrackersServerConfig {
     pub name: String,
     #[serde(rename = "IGServerType")]
     pub ig_server_type: ServerType,
-    #[serde(rename = "TrackersConfig")]
+    #[serde(rename = "BaseStationsConfig")]
     pub base_stations_config: BaseStationsConfig,
     #[serde(rename = "TrackersConfig")]
     pub trackers_config: TrackersConfig,
@@ -172,6 +474,24 @@ pub struct TrackersServerConfig {
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct EntityTrackersConfig(pub Vec<EntityTrackers>);
 
+impl From<BaseStationsConfigFormat> for BaseStationsConfig {
+    fn from(base_stations_config_format: BaseStationsConfigFormat) -> BaseStationsConfig {
+        match base_stations_config_format {
+            BaseStationsConfigFormat::Named(base_stations) => BaseStationsConfig(base_stations),
+            BaseStationsConfigFormat::TwoStations { station_a, station_b } => BaseStationsConfig(
+                vec![(LEGACY_STATION_A_NAME, station_a), (LEGACY_STATION_B_NAME, station_b)]
+                    .into_iter()
+                    .map(|(station_name, legacy_base_station)| BaseStations {
+                        station_name: station_name.into(),
+                        position: legacy_base_station.position,
+                        rotation: legacy_base_station.rotation,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
 impl Into<SPDataBits> for DataBits {
     fn into(self) -> SPDataBits {
         match self {
@@ -235,12 +555,60 @@ impl JsonSerializable<'_> for Parity {}
 impl JsonSerializable<'_> for StopBits {}
 impl JsonSerializable<'_> for SerialPortSettings {}
 impl JsonSerializable<'_> for ServerType {}
+pub fn default_tracking_reprojection_threshold() -> f64 {
+    2.0
+}
+
+/// The original four-LED constellation, ±11.4 on the tracker's XZ plane.
+pub fn default_marker_constellation() -> Vec<SafePoint3D> {
+    vec![
+        SafePoint3D::new(11.4, 0.0, -11.4),
+        SafePoint3D::new(11.4, 0.0, 11.4),
+        SafePoint3D::new(-11.4, 0.0, 11.4),
+        SafePoint3D::new(-11.4, 0.0, -11.4),
+    ]
+}
+
+impl SafePoint3D {
+    pub fn new(x: f64, y: f64, z: f64) -> SafePoint3D {
+        SafePoint3D { x, y, z }
+    }
+}
+
+impl CameraIntrinsics {
+    pub fn camera_matrix(&self) -> [[f64; 3]; 3] {
+        [[self.fx, 0.0, self.cx], [0.0, self.fy, self.cy], [0.0, 0.0, 1.0]]
+    }
+}
+
 impl JsonSerializable<'_> for SafePoint2D {}
 impl JsonSerializable<'_> for SafePoint3D {}
 impl JsonSerializable<'_> for SafeEulerAngles {}
+impl JsonSerializable<'_> for SafeQuaternion {}
+impl JsonSerializable<'_> for AxisConvention {}
+impl JsonSerializable<'_> for AngleUnit {}
+impl JsonSerializable<'_> for PoseConvention {}
+impl JsonSerializable<'_> for Pose {}
+impl JsonSerializable<'_> for PoseQuality {}
+impl JsonSerializable<'_> for PoseEstimate {}
 impl JsonSerializable<'_> for BaseStations {}
 impl JsonSerializable<'_> for BaseStationsConfig {}
+impl JsonSerializable<'_> for CalibrationObservation {}
+impl JsonSerializable<'_> for CalibrationObservations {}
 impl JsonSerializable<'_> for TrackerCommunication {}
+impl JsonSerializable<'_> for DistortionModel {}
+impl JsonSerializable<'_> for UndistortionMode {}
+impl JsonSerializable<'_> for CameraIntrinsics {}
+impl JsonSerializable<'_> for ImageResidual {}
+impl JsonSerializable<'_> for CameraCalibration {}
+impl JsonSerializable<'_> for PnPMethod {}
+impl JsonSerializable<'_> for RansacConfig {}
+impl JsonSerializable<'_> for PoseSolverConfig {}
+impl JsonSerializable<'_> for OneEuroFilterConfig {}
+impl JsonSerializable<'_> for KalmanFilterConfig {}
+impl JsonSerializable<'_> for PoseFilterConfig {}
+impl JsonSerializable<'_> for BlobDetectorConfig {}
+impl JsonSerializable<'_> for PosePredictorConfig {}
 impl JsonSerializable<'_> for TrackerEndpoint {}
 impl JsonSerializable<'_> for TrackersConfig {}
 impl JsonSerializable<'_> for TrackersServerConfig {}
@@ -307,6 +675,48 @@ impl Display for SafeEulerAngles {
     }
 }
 
+impl Display for SafeQuaternion {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for AxisConvention {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for AngleUnit {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for PoseConvention {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for Pose {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for PoseQuality {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for PoseEstimate {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
 impl Display for BaseStations {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
         write!(formatter, "{}", self.to_json())
@@ -319,12 +729,102 @@ impl Display for BaseStationsConfig {
     }
 }
 
+impl Display for CalibrationObservation {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for CalibrationObservations {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
 impl Display for TrackerCommunication {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
         write!(formatter, "{}", self.to_json())
     }
 }
 
+impl Display for DistortionModel {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for UndistortionMode {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for CameraIntrinsics {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for ImageResidual {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for CameraCalibration {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for PnPMethod {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for RansacConfig {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for PoseSolverConfig {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for OneEuroFilterConfig {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for KalmanFilterConfig {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for PoseFilterConfig {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for BlobDetectorConfig {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
+impl Display for PosePredictorConfig {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        write!(formatter, "{}", self.to_json())
+    }
+}
+
 impl Display for TrackerEndpoint {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
         write!(formatter, "{}", self.to_json())
@@ -427,9 +927,76 @@ impl Default for SafeEulerAngles {
     }
 }
 
+impl Default for SafeQuaternion {
+    fn default() -> SafeQuaternion {
+        SafeQuaternion {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+}
+
+impl Default for AxisConvention {
+    fn default() -> AxisConvention {
+        AxisConvention::OpenCV
+    }
+}
+
+impl Default for AngleUnit {
+    fn default() -> AngleUnit {
+        AngleUnit::Degrees
+    }
+}
+
+impl Default for PoseConvention {
+    fn default() -> PoseConvention {
+        PoseConvention {
+            axis_convention: Default::default(),
+            angle_unit: Default::default(),
+            length_scale: 1.0,
+        }
+    }
+}
+
+impl Default for Pose {
+    fn default() -> Pose {
+        Pose {
+            position: Default::default(),
+            orientation: Default::default(),
+            quaternion: Default::default(),
+            rotation_matrix: IDENTITY_MATRIX,
+        }
+    }
+}
+
+impl Default for PoseQuality {
+    fn default() -> PoseQuality {
+        PoseQuality {
+            reprojection_errors: Default::default(),
+            rms_reprojection_error: 0.0,
+            condition_number: 1.0,
+            is_degenerate: false,
+            confidence: 0.0,
+        }
+    }
+}
+
+impl Default for PoseEstimate {
+    fn default() -> PoseEstimate {
+        PoseEstimate {
+            pose: Default::default(),
+            inliers: Default::default(),
+            quality: Default::default(),
+        }
+    }
+}
+
 impl Default for BaseStations {
     fn default() -> BaseStations {
         BaseStations {
+            station_name: Default::default(),
             position: Default::default(),
             rotation: Default::default(),
         }
@@ -438,10 +1005,16 @@ impl Default for BaseStations {
 
 impl Default for BaseStationsConfig {
     fn default() -> BaseStationsConfig {
-        BaseStationsConfig {
-            station_a: Default::default(),
-            station_b: Default::default(),
-        }
+        BaseStationsConfig(vec![
+            BaseStations {
+                station_name: LEGACY_STATION_A_NAME.into(),
+                ..Default::default()
+            },
+            BaseStations {
+                station_name: LEGACY_STATION_B_NAME.into(),
+                ..Default::default()
+            },
+        ])
     }
 }
 
@@ -451,11 +1024,131 @@ impl Default for TrackerCommunication {
     }
 }
 
+impl Default for CameraIntrinsics {
+    fn default() -> CameraIntrinsics {
+        CameraIntrinsics {
+            fx: 10.0,
+            fy: 10.0,
+            cx: 5.0,
+            cy: 5.0,
+            distortion_coefficients: [0.0; 5],
+            distortion_model: Default::default(),
+        }
+    }
+}
+
+impl Default for DistortionModel {
+    fn default() -> DistortionModel {
+        DistortionModel::RadialTangential
+    }
+}
+
+impl Default for UndistortionMode {
+    fn default() -> UndistortionMode {
+        UndistortionMode::InSolver
+    }
+}
+
+impl Default for CameraCalibration {
+    fn default() -> CameraCalibration {
+        CameraCalibration {
+            camera_intrinsics: Default::default(),
+            image_width: 0,
+            image_height: 0,
+            rms_reprojection_error: 0.0,
+            image_residuals: vec![],
+            skipped_images: vec![],
+            unreadable_images: vec![],
+        }
+    }
+}
+
+impl Default for PnPMethod {
+    fn default() -> PnPMethod {
+        PnPMethod::Iterative
+    }
+}
+
+impl Default for RansacConfig {
+    fn default() -> RansacConfig {
+        RansacConfig {
+            iterations_count: 100,
+            reprojection_error: 8.0,
+            confidence: 0.99,
+        }
+    }
+}
+
+impl Default for PoseSolverConfig {
+    fn default() -> PoseSolverConfig {
+        PoseSolverConfig {
+            pnp_method: Default::default(),
+            ransac: None,
+            tracking_reprojection_threshold: default_tracking_reprojection_threshold(),
+            undistortion: Default::default(),
+        }
+    }
+}
+
+impl Default for OneEuroFilterConfig {
+    fn default() -> OneEuroFilterConfig {
+        OneEuroFilterConfig {
+            min_cutoff: 1.0,
+            beta: 0.05,
+            derivative_cutoff: 1.0,
+        }
+    }
+}
+
+impl Default for KalmanFilterConfig {
+    fn default() -> KalmanFilterConfig {
+        KalmanFilterConfig {
+            position_process_noise: 1.0,
+            position_measurement_noise: 0.01,
+            orientation_process_noise: 1.0,
+            orientation_measurement_noise: 0.0001,
+        }
+    }
+}
+
+impl Default for PoseFilterConfig {
+    fn default() -> PoseFilterConfig {
+        PoseFilterConfig::None
+    }
+}
+
+impl Default for BlobDetectorConfig {
+    fn default() -> BlobDetectorConfig {
+        BlobDetectorConfig {
+            threshold: 200.0,
+            min_area: 2.0,
+            max_area: 500.0,
+            min_circularity: 0.5,
+        }
+    }
+}
+
+impl Default for PosePredictorConfig {
+    fn default() -> PosePredictorConfig {
+        PosePredictorConfig {
+            max_prediction_interval: 100.0,
+            velocity_time_constant: 20.0,
+        }
+    }
+}
+
 impl Default for TrackerEndpoint {
     fn default() -> TrackerEndpoint {
         TrackerEndpoint {
             tracker_name: Default::default(),
             tracker_communication: Default::default(),
+            camera_intrinsics: Default::default(),
+            camera_calibration_path: None,
+            marker_constellation: default_marker_constellation(),
+            pose_solver_config: Default::default(),
+            pose_filter_config: Default::default(),
+            pose_predictor_config: Default::default(),
+            blob_detector_config: None,
         }
     }
 }
@@ -466,14 +1159,35 @@ impl Default for TrackersConfig {
             TrackerEndpoint {
                 tracker_name: "HeadTracker".into(),
                 tracker_communication: TrackerCommunication::SerialPort(Default::default()),
+                camera_intrinsics: Default::default(),
+                camera_calibration_path: None,
+                marker_constellation: default_marker_constellation(),
+                pose_solver_config: Default::default(),
+                pose_filter_config: Default::default(),
+                pose_predictor_config: Default::default(),
+                blob_detector_config: None,
             },
             TrackerEndpoint {
                 tracker_name: "ArmTracker".into(),
                 tracker_communication: TrackerCommunication::TCP("127.0.0.1:3000".parse().unwrap()),
+                camera_intrinsics: Default::default(),
+                camera_calibration_path: None,
+                marker_constellation: default_marker_constellation(),
+                pose_solver_config: Default::default(),
+                pose_filter_config: Default::default(),
+                pose_predictor_config: Default::default(),
+                blob_detector_config: None,
             },
             TrackerEndpoint {
                 tracker_name: "FootTracker".into(),
                 tracker_communication: TrackerCommunication::UDP("127.0.0.1:2000".parse().unwrap()),
+                camera_intrinsics: Default::default(),
+                camera_calibration_path: None,
+                marker_constellation: default_marker_constellation(),
+                pose_solver_config: Default::default(),
+                pose_filter_config: Default::default(),
+                pose_predictor_config: Default::default(),
+                blob_detector_config: None,
             },
         ])
     }
@@ -0,0 +1,171 @@
+use crate::geometry::{
+    multiply_matrices, pose_from_quaternion, quaternion_from_rotation_matrix, rotate, rotation_matrix_from_euler_angles,
+};
+use crate::types::{AngleUnit, BaseStations, BaseStationsConfig, Pose, RotationMatrix, SafePoint3D};
+
+/// Placement of a base-station camera in the world frame. The station's `Position` is its origin in world
+/// coordinates and its `Rotation` (intrinsic Z-Y-X, in degrees) turns station-frame axes into world axes. Both use
+/// the axis convention and length unit of the poses being transformed.
+#[derive(Debug, Clone)]
+pub struct BaseStationTransform {
+    rotation_matrix: RotationMatrix,
+    position: SafePoint3D,
+}
+
+/// Every configured base station by name, so poses solved relative to any station camera land in one shared frame.
+#[derive(Debug, Clone)]
+pub struct WorldFrame {
+    base_station_transforms: Vec<(String, BaseStationTransform)>,
+}
+
+impl BaseStationTransform {
+    pub fn new(base_station: &BaseStations) -> BaseStationTransform {
+        BaseStationTransform {
+            rotation_matrix: rotation_matrix_from_euler_angles(&base_station.rotation, AngleUnit::Degrees),
+            position: base_station.position.clone(),
+        }
+    }
+
+    pub fn point_to_world(&self, point: &SafePoint3D) -> SafePoint3D {
+        let rotated_point = rotate(&self.rotation_matrix, point);
+
+        SafePoint3D::new(
+            rotated_point.x + self.position.x,
+            rotated_point.y + self.position.y,
+            rotated_point.z + self.position.z,
+        )
+    }
+
+    /// `angle_unit` is the unit of the pose's Euler angles, which the world pose keeps.
+    pub fn pose_to_world(&self, pose: &Pose, angle_unit: AngleUnit) -> Pose {
+        let rotation_matrix = multiply_matrices(&self.rotation_matrix, &pose.rotation_matrix);
+
+        pose_from_quaternion(
+            self.point_to_world(&pose.position),
+            &quaternion_from_rotation_matrix(&rotation_matrix),
+            angle_unit,
+        )
+    }
+}
+
+impl WorldFrame {
+    pub fn new(base_stations_config: &BaseStationsConfig) -> WorldFrame {
+        WorldFrame {
+            base_station_transforms: base_stations_config
+                .0
+                .iter()
+                .map(|base_station| {
+                    (
+                        base_station.station_name.clone(),
+                        BaseStationTransform::new(base_station),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn base_station_names(&self) -> impl Iterator<Item = &str> {
+        self.base_station_transforms.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn base_station_transform(&self, base_station_name: &str) -> Option<&BaseStationTransform> {
+        self.base_station_transforms
+            .iter()
+            .find(|(name, _)| name == base_station_name)
+            .map(|(_, base_station_transform)| base_station_transform)
+    }
+
+    /// `None` when no base station has that name.
+    pub fn pose_to_world(&self, base_station_name: &str, pose: &Pose, angle_unit: AngleUnit) -> Option<Pose> {
+        self.base_station_transform(base_station_name)
+            .map(|base_station_transform| base_station_transform.pose_to_world(pose, angle_unit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{quaternion_angle, quaternion_from_rotation_vector, transpose};
+    use crate::types::SafeEulerAngles;
+
+    #[test]
+    fn stations_agree_on_world_pose() {
+        let base_stations_config = BaseStationsConfig(vec![
+            BaseStations {
+                station_name: "StationA".into(),
+                position: SafePoint3D::new(1.0, 2.0, 3.0),
+                rotation: SafeEulerAngles {
+                    roll: 0.0,
+                    pitch: 0.0,
+                    yaw: 90.0,
+                },
+            },
+            BaseStations {
+                station_name: "StationB".into(),
+                position: SafePoint3D::new(-4.0, 0.5, 1.0),
+                rotation: SafeEulerAngles {
+                    roll: 10.0,
+                    pitch: -20.0,
+                    yaw: 200.0,
+                },
+            },
+            BaseStations {
+                station_name: "Ceiling".into(),
+                position: SafePoint3D::new(0.0, 0.0, -3.0),
+                rotation: SafeEulerAngles {
+                    roll: 180.0,
+                    pitch: 90.0,
+                    yaw: 0.0,
+                },
+            },
+        ]);
+        let world_frame = WorldFrame::new(&base_stations_config);
+        let world_pose = pose_from_quaternion(
+            SafePoint3D::new(0.5, -1.0, 2.0),
+            &quaternion_from_rotation_vector(&[0.2, -0.3, 0.4]),
+            AngleUnit::Degrees,
+        );
+
+        // Express the world pose relative to each station, as its camera would have solved it
+        let station_poses: Vec<(&str, Pose)> = world_frame
+            .base_station_transforms
+            .iter()
+            .map(|(name, base_station_transform)| {
+                let inverse_rotation = transpose(&base_station_transform.rotation_matrix);
+                let position = rotate(
+                    &inverse_rotation,
+                    &SafePoint3D::new(
+                        world_pose.position.x - base_station_transform.position.x,
+                        world_pose.position.y - base_station_transform.position.y,
+                        world_pose.position.z - base_station_transform.position.z,
+                    ),
+                );
+                let rotation_matrix = multiply_matrices(&inverse_rotation, &world_pose.rotation_matrix);
+                let station_pose = pose_from_quaternion(
+                    position,
+                    &quaternion_from_rotation_matrix(&rotation_matrix),
+                    AngleUnit::Degrees,
+                );
+                (name.as_str(), station_pose)
+            })
+            .collect();
+
+        assert!((station_poses[0].1.position.x - -3.0).abs() < 1e-9);
+        assert!((station_poses[0].1.position.y - 0.5).abs() < 1e-9);
+
+        for (name, station_pose) in &station_poses {
+            let pose = world_frame
+                .pose_to_world(name, station_pose, AngleUnit::Degrees)
+                .unwrap();
+            assert!((pose.position.x - world_pose.position.x).abs() < 1e-9);
+            assert!((pose.position.y - world_pose.position.y).abs() < 1e-9);
+            assert!((pose.position.z - world_pose.position.z).abs() < 1e-9);
+            assert!(quaternion_angle(&pose.quaternion, &world_pose.quaternion) < 1e-9);
+            assert!((pose.orientation.yaw - world_pose.orientation.yaw).abs() < 1e-6);
+        }
+
+        assert!(world_frame
+            .pose_to_world("StationD", &world_pose, AngleUnit::Degrees)
+            .is_none());
+    }
+}
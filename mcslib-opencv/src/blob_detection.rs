@@ -0,0 +1,318 @@
+use crate::mat_from_gray_buffer;
+use mcslib_common::types::{BlobDetectorConfig, SafePoint2D};
+use opencv::core::{Mat, CV_32S};
+use opencv::imgcodecs::{imread, IMREAD_GRAYSCALE};
+use opencv::imgproc::{
+    connected_components_with_stats, threshold, CC_STAT_AREA, CC_STAT_HEIGHT, CC_STAT_LEFT, CC_STAT_TOP, CC_STAT_WIDTH,
+    THRESH_BINARY,
+};
+use opencv::Error as OpenCVError;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FormatterResult};
+use std::path::Path;
+
+const CONNECTIVITY: i32 = 8;
+
+/// A bright spot in a grayscale frame. `center` is the sub-pixel centroid of the pixel intensities above the
+/// threshold, `area` the pixel count and `circularity` the minor to major axis ratio of the blob.
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub center: SafePoint2D,
+    pub area: f64,
+    pub circularity: f64,
+}
+
+#[derive(Debug)]
+pub enum BlobDetectionError {
+    BufferSizeMismatch { expected: usize, actual: usize },
+    EmptyImage(String),
+    ImageTooLarge { width: usize, height: usize },
+    OpenCV(OpenCVError),
+}
+
+impl Display for BlobDetectionError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            BlobDetectionError::BufferSizeMismatch { expected, actual } => write!(
+                formatter,
+                "Expected a grayscale buffer of {} byte(s), got {}",
+                expected, actual
+            ),
+            BlobDetectionError::EmptyImage(image_path) => write!(formatter, "Cannot read image \"{}\"", image_path),
+            BlobDetectionError::ImageTooLarge { width, height } => write!(
+                formatter,
+                "A {}x{} frame exceeds the largest image OpenCV can address",
+                width, height
+            ),
+            BlobDetectionError::OpenCV(error) => write!(formatter, "OpenCV error: {}", error),
+        }
+    }
+}
+
+impl Error for BlobDetectionError {}
+
+impl From<OpenCVError> for BlobDetectionError {
+    fn from(error: OpenCVError) -> BlobDetectionError {
+        BlobDetectionError::OpenCV(error)
+    }
+}
+
+/// Intensity-weighted raw moments of one blob, up to second order.
+#[derive(Debug, Clone, Default)]
+struct BlobMoments {
+    pixel_count: usize,
+    weight: f64,
+    weighted_x: f64,
+    weighted_y: f64,
+    weighted_xx: f64,
+    weighted_xy: f64,
+    weighted_yy: f64,
+}
+
+impl BlobMoments {
+    fn add_pixel(&mut self, x: f64, y: f64, weight: f64) {
+        self.pixel_count += 1;
+        self.weight += weight;
+        self.weighted_x += weight * x;
+        self.weighted_y += weight * y;
+        self.weighted_xx += weight * x * x;
+        self.weighted_xy += weight * x * y;
+        self.weighted_yy += weight * y * y;
+    }
+
+    fn to_blob(&self) -> Option<Blob> {
+        if self.pixel_count == 0 || self.weight <= 0.0 {
+            return None;
+        }
+
+        let center_x = self.weighted_x / self.weight;
+        let center_y = self.weighted_y / self.weight;
+        let variance_x = (self.weighted_xx / self.weight - center_x * center_x).max(0.0);
+        let variance_y = (self.weighted_yy / self.weight - center_y * center_y).max(0.0);
+        let covariance = self.weighted_xy / self.weight - center_x * center_y;
+        // Eigenvalues of the 2x2 covariance, the squared axis lengths of the blob's ellipse
+        let half_trace = (variance_x + variance_y) / 2.0;
+        let spread = ((variance_x - variance_y).powi(2) / 4.0 + covariance * covariance).sqrt();
+        let major_variance = half_trace + spread;
+        let minor_variance = (half_trace - spread).max(0.0);
+        let circularity = if major_variance > 0.0 {
+            (minor_variance / major_variance).sqrt()
+        } else {
+            1.0
+        };
+
+        Some(Blob {
+            center: SafePoint2D {
+                x: center_x,
+                y: center_y,
+            },
+            area: self.pixel_count as f64,
+            circularity,
+        })
+    }
+}
+
+fn is_accepted(blob: &Blob, blob_detector_config: &BlobDetectorConfig) -> bool {
+    blob.area >= blob_detector_config.min_area
+        && blob.area <= blob_detector_config.max_area
+        && blob.circularity >= blob_detector_config.min_circularity
+}
+
+/// Finds bright blobs in an 8-bit single channel frame, largest first. Pixels are weighted by how far they exceed
+/// the threshold, so the centroid of a blurred LED lands between pixel centers.
+pub fn detect_blobs(image: &Mat, blob_detector_config: &BlobDetectorConfig) -> Result<Vec<Blob>, OpenCVError> {
+    let mut binary_image = Mat::default()?;
+    threshold(
+        image,
+        &mut binary_image,
+        blob_detector_config.threshold,
+        255.0,
+        THRESH_BINARY,
+    )?;
+
+    let mut labels = Mat::default()?;
+    let mut stats = Mat::default()?;
+    let mut centroids = Mat::default()?;
+    let label_count = connected_components_with_stats(
+        &binary_image,
+        &mut labels,
+        &mut stats,
+        &mut centroids,
+        CONNECTIVITY,
+        CV_32S,
+    )?;
+    let mut blobs = vec![];
+
+    // Label 0 is the background
+    for label in 1..label_count {
+        let area = f64::from(*stats.at_2d::<i32>(label, CC_STAT_AREA)?);
+
+        if area < blob_detector_config.min_area || area > blob_detector_config.max_area {
+            continue;
+        }
+
+        let left = *stats.at_2d::<i32>(label, CC_STAT_LEFT)?;
+        let top = *stats.at_2d::<i32>(label, CC_STAT_TOP)?;
+        let width = *stats.at_2d::<i32>(label, CC_STAT_WIDTH)?;
+        let height = *stats.at_2d::<i32>(label, CC_STAT_HEIGHT)?;
+        let mut blob_moments = BlobMoments::default();
+
+        for row in top..top + height {
+            for col in left..left + width {
+                if *labels.at_2d::<i32>(row, col)? != label {
+                    continue;
+                }
+
+                let intensity = f64::from(*image.at_2d::<u8>(row, col)?);
+                blob_moments.add_pixel(
+                    f64::from(col),
+                    f64::from(row),
+                    intensity - blob_detector_config.threshold,
+                );
+            }
+        }
+
+        if let Some(blob) = blob_moments
+            .to_blob()
+            .filter(|blob| is_accepted(blob, blob_detector_config))
+        {
+            blobs.push(blob);
+        }
+    }
+
+    blobs.sort_by(|a, b| b.area.partial_cmp(&a.area).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(blobs)
+}
+
+/// Detects blobs in an image file, read as grayscale.
+pub fn detect_blobs_in_file(
+    image_path: &Path,
+    blob_detector_config: &BlobDetectorConfig,
+) -> Result<Vec<Blob>, BlobDetectionError> {
+    let image_path = image_path.to_string_lossy();
+    let image = imread(&image_path, IMREAD_GRAYSCALE)?;
+
+    if image.empty()? {
+        return Err(BlobDetectionError::EmptyImage(image_path.into_owned()));
+    }
+
+    Ok(detect_blobs(&image, blob_detector_config)?)
+}
+
+/// Detects blobs in a raw 8-bit grayscale frame stored row by row without padding, as cameras stream them.
+pub fn detect_blobs_in_buffer(
+    buffer: &[u8],
+    width: usize,
+    height: usize,
+    blob_detector_config: &BlobDetectorConfig,
+) -> Result<Vec<Blob>, BlobDetectionError> {
+    if i32::try_from(width).is_err() || i32::try_from(height).is_err() {
+        return Err(BlobDetectionError::ImageTooLarge { width, height });
+    }
+
+    let expected = width
+        .checked_mul(height)
+        .ok_or(BlobDetectionError::ImageTooLarge { width, height })?;
+
+    if buffer.len() != expected {
+        return Err(BlobDetectionError::BufferSizeMismatch {
+            expected,
+            actual: buffer.len(),
+        });
+    }
+
+    if buffer.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let image = mat_from_gray_buffer(buffer, width, height)?;
+
+    Ok(detect_blobs(&image, blob_detector_config)?)
+}
+
+/// Blob centers in detection order, ready for `compute_pose`.
+pub fn image_points(blobs: &[Blob]) -> Vec<SafePoint2D> {
+    blobs.iter().map(|blob| blob.center.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_moments_give_sub_pixel_center_and_circularity() {
+        let mut round_blob_moments = BlobMoments::default();
+
+        for (x, y, weight) in &[
+            (10.0, 20.0, 50.0),
+            (11.0, 20.0, 30.0),
+            (9.0, 20.0, 10.0),
+            (10.0, 21.0, 20.0),
+            (10.0, 19.0, 20.0),
+        ] {
+            round_blob_moments.add_pixel(*x, *y, *weight);
+        }
+
+        let blob = round_blob_moments.to_blob().unwrap();
+        assert!((blob.center.x - 10.153_846).abs() < 1e-6);
+        assert!((blob.center.y - 20.0).abs() < 1e-9);
+        assert!((blob.area - 5.0).abs() < f64::EPSILON);
+        assert!(blob.circularity > 0.8);
+
+        let mut line_blob_moments = BlobMoments::default();
+
+        for x in 0..6 {
+            line_blob_moments.add_pixel(f64::from(x), 3.0, 10.0);
+        }
+
+        let blob = line_blob_moments.to_blob().unwrap();
+        assert!((blob.center.x - 2.5).abs() < 1e-9);
+        assert!(blob.circularity < 1e-6);
+        assert!(!is_accepted(&blob, &BlobDetectorConfig::default()));
+        assert!(BlobMoments::default().to_blob().is_none());
+    }
+
+    #[test]
+    fn gaussian_spots_are_detected_in_a_buffer() {
+        let (width, height) = (64, 48);
+        let mut buffer = vec![0u8; width * height];
+
+        for (center_x, center_y, sigma) in &[(20.3, 15.7, 1.5), (45.6, 30.2, 2.5)] {
+            for row in 0..height {
+                for col in 0..width {
+                    let squared_distance = (col as f64 - center_x).powi(2) + (row as f64 - center_y).powi(2);
+                    let intensity = (250.0 * (-squared_distance / (2.0 * sigma * sigma)).exp()).round() as u8;
+                    buffer[row * width + col] = buffer[row * width + col].max(intensity);
+                }
+            }
+        }
+
+        // A single hot pixel, below the minimum area
+        buffer[40 * width + 5] = 255;
+
+        let blob_detector_config = BlobDetectorConfig {
+            threshold: 50.0,
+            ..Default::default()
+        };
+        let blobs = detect_blobs_in_buffer(&buffer, width, height, &blob_detector_config).unwrap();
+        assert_eq!(blobs.len(), 2);
+        assert!(blobs[0].area > blobs[1].area);
+        assert!((blobs[0].center.x - 45.6).abs() < 0.05 && (blobs[0].center.y - 30.2).abs() < 0.05);
+        assert!((blobs[1].center.x - 20.3).abs() < 0.05 && (blobs[1].center.y - 15.7).abs() < 0.05);
+        assert!(blobs.iter().all(|blob| blob.circularity > 0.9));
+
+        match detect_blobs_in_buffer(&buffer, width, height - 1, &blob_detector_config) {
+            Err(BlobDetectionError::BufferSizeMismatch {
+                expected: 2944,
+                actual: 3072,
+            }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        match detect_blobs_in_buffer(&[], 1 << 31, 0, &blob_detector_config) {
+            Err(BlobDetectionError::ImageTooLarge { .. }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,873 @@
+use crate::motion_tracker_compute::{rms, Extrinsics, PoseSolver, PoseSolverError};
+use mcslib_common::config::MIN_MARKER_CONSTELLATION_POINTS;
+use mcslib_common::geometry::{centroid, distance, subtract, symmetric_eigen};
+use mcslib_common::types::{CameraIntrinsics, PoseEstimate, SafePoint2D, SafePoint3D};
+use std::collections::HashSet;
+
+/// Seed hypotheses tried per solve, enough for every ordering of a four-marker seed among 15 image points.
+pub const MAX_SEED_ASSIGNMENTS: usize = 32_760;
+/// The runner-up assignment makes a solution ambiguous when its RMS reprojection error is within this factor of the
+/// best one.
+pub const AMBIGUITY_RATIO: f64 = 2.0;
+/// Lower bound on the best RMS reprojection error in the ambiguity test, in px, so two near-perfect fits of a
+/// symmetric constellation still count as ambiguous.
+const AMBIGUITY_ERROR_FLOOR: f64 = 0.1;
+/// A seed pose must project every marker within this many `TrackingReprojectionThreshold`s of an image point. The
+/// seed only fits four markers, so the others get some slack before the full refinement.
+const HYPOTHESIS_GATE_FACTOR: f64 = 4.0;
+const SEED_SIZE: usize = MIN_MARKER_CONSTELLATION_POINTS;
+/// Largest ratio between the two image scales of a 3D seed's affine fit, which perspective pulls apart when the
+/// constellation's depth spread is a sizeable fraction of its distance.
+const SEED_SCALE_TOLERANCE: f64 = 1.5;
+/// Largest relative deviation of a planar seed's homography from a scaled rotation, for blob noise.
+const SEED_ROTATION_TOLERANCE: f64 = 0.2;
+/// Largest distance from a marker predicted by the seed fit to an image point, relative to the RMS radius of the seed
+/// in the image.
+const SEED_PREDICTION_TOLERANCE: f64 = 0.25;
+/// Seeds whose smallest scatter eigenvalue is below this fraction of the largest count as planar.
+const PLANAR_SEED_TOLERANCE: f64 = 1e-6;
+
+/// The pose of the best marker assignment. `assignment[i]` is the index of the image point matched to object point
+/// `i`, and `pose_estimate` refers to image points in object point order.
+#[derive(Debug, Clone)]
+pub struct Correspondence {
+    pub assignment: Vec<usize>,
+    pub pose_estimate: PoseEstimate,
+    pub rms_reprojection_error: f64,
+    pub runner_up: Option<RunnerUpAssignment>,
+    pub is_ambiguous: bool,
+}
+
+/// The second best assignment, kept to judge how distinct the best one is.
+#[derive(Debug, Clone)]
+pub struct RunnerUpAssignment {
+    pub assignment: Vec<usize>,
+    pub rms_reprojection_error: f64,
+}
+
+/// Number of ways to match `object_point_count` markers to distinct image points, or `None` on overflow.
+pub fn assignment_count(image_point_count: usize, object_point_count: usize) -> Option<usize> {
+    if object_point_count > image_point_count {
+        return Some(0);
+    }
+
+    (image_point_count - object_point_count + 1..=image_point_count)
+        .try_fold(1usize, |count, factor| count.checked_mul(factor))
+}
+
+/// Injective assignments of `length` slots to distinct indices below `pool_size`, generated lazily in lexicographic
+/// order.
+#[derive(Debug, Clone)]
+struct Assignments {
+    pool_size: usize,
+    length: usize,
+    indices: Vec<usize>,
+    is_used: Vec<bool>,
+    is_started: bool,
+}
+
+impl Assignments {
+    fn new(pool_size: usize, length: usize) -> Assignments {
+        Assignments {
+            pool_size,
+            length,
+            indices: Vec::with_capacity(length),
+            is_used: vec![false; pool_size],
+            is_started: false,
+        }
+    }
+}
+
+impl Iterator for Assignments {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if !self.is_started {
+            self.is_started = true;
+
+            if self.length > self.pool_size {
+                return None;
+            }
+
+            self.indices = (0..self.length).collect();
+            self.is_used
+                .iter_mut()
+                .take(self.length)
+                .for_each(|is_used| *is_used = true);
+            return Some(self.indices.clone());
+        }
+
+        // Advance the rightmost slot that can still take a larger free index, then refill the slots after it
+        for position in (0..self.indices.len()).rev() {
+            let current = self.indices[position];
+            self.is_used[current] = false;
+
+            if let Some(next) = (current + 1..self.pool_size).find(|index| !self.is_used[*index]) {
+                self.indices[position] = next;
+                self.is_used[next] = true;
+
+                for tail_position in position + 1..self.length {
+                    if let Some(free) = (0..self.pool_size).find(|index| !self.is_used[*index]) {
+                        self.indices[tail_position] = free;
+                        self.is_used[free] = true;
+                    }
+                }
+
+                return Some(self.indices.clone());
+            }
+        }
+
+        self.indices.clear();
+        None
+    }
+}
+
+/// Picks `SEED_SIZE` well spread markers: the one furthest from the centroid, then repeatedly the one furthest from
+/// those already picked.
+fn seed_object_indices(object_points: &[SafePoint3D]) -> Vec<usize> {
+    let center = centroid(object_points);
+    let mut seed = vec![];
+    let mut spread: Vec<f64> = object_points.iter().map(|point| distance(point, &center)).collect();
+
+    while seed.len() < SEED_SIZE.min(object_points.len()) {
+        let next = (0..object_points.len())
+            .filter(|index| !seed.contains(index))
+            .max_by(|left, right| spread[*left].total_cmp(&spread[*right]))
+            .unwrap_or_default();
+        seed.push(next);
+
+        for (index, point) in object_points.iter().enumerate() {
+            let point_distance = distance(point, &object_points[next]);
+            spread[index] = if seed.len() == 1 {
+                point_distance
+            } else {
+                spread[index].min(point_distance)
+            };
+        }
+    }
+
+    seed
+}
+
+/// Rejects seed hypotheses no pose could produce, before any solver runs. A seed spanning 3D is fitted with a scaled
+/// orthographic camera, an affine map `u = A q + c` from marker offsets `q` around the seed's centroid, which is
+/// close to the true projection when the constellation is small next to its distance; the two singular values of
+/// `A` must then nearly agree. A planar seed is fitted exactly with the homography from its plane to normalized image
+/// coordinates, whose first two columns must be orthogonal and of equal length to come from a rotation. Either map
+/// must then put every other marker it can place near some image point.
+#[derive(Debug, Clone)]
+struct SeedFilter {
+    /// Seed marker offsets from the seed centroid along the principal axes of the seed, two for a planar seed.
+    seed_offsets: Vec<Vec<f64>>,
+    /// Offsets of the markers outside the seed that the fitted map can place.
+    other_offsets: Vec<Vec<f64>>,
+    /// Least squares weights of a 3D seed, `A = Σ u_k weights[k]ᵀ` for centred seed image points `u_k`.
+    weights: Vec<Vec<f64>>,
+    camera_intrinsics: CameraIntrinsics,
+}
+
+impl SeedFilter {
+    fn new(
+        camera_intrinsics: &CameraIntrinsics,
+        object_points: &[SafePoint3D],
+        seed_object_indices: &[usize],
+    ) -> SeedFilter {
+        let seed_points: Vec<SafePoint3D> = seed_object_indices
+            .iter()
+            .map(|index| object_points[*index].clone())
+            .collect();
+        let center = centroid(&seed_points);
+        let mut scatter = vec![vec![0.0; 3]; 3];
+
+        for point in &seed_points {
+            let offset = subtract(point, &center);
+            let offset = [offset.x, offset.y, offset.z];
+
+            for row in 0..3 {
+                for col in 0..3 {
+                    scatter[row][col] += offset[row] * offset[col];
+                }
+            }
+        }
+
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&scatter);
+        let is_planar = eigenvalues[2] <= eigenvalues[0] * PLANAR_SEED_TOLERANCE;
+        let axes = if is_planar {
+            &eigenvectors[..2]
+        } else {
+            &eigenvectors[..]
+        };
+        let offset_of = |point: &SafePoint3D| -> Vec<f64> {
+            let offset = subtract(point, &center);
+            axes.iter()
+                .map(|axis| axis[0] * offset.x + axis[1] * offset.y + axis[2] * offset.z)
+                .collect()
+        };
+        let seed_offsets: Vec<Vec<f64>> = seed_points.iter().map(&offset_of).collect();
+        // In the eigenvector basis the scatter is diagonal, so its inverse just divides by the eigenvalues
+        let weights = seed_offsets
+            .iter()
+            .map(|offset| {
+                offset
+                    .iter()
+                    .zip(eigenvalues.iter())
+                    .map(|(coordinate, eigenvalue)| coordinate / eigenvalue)
+                    .collect()
+            })
+            .collect();
+        // Markers off the plane of a planar seed depend on the tilt out of that plane, which the homography leaves out
+        let other_offsets = (0..object_points.len())
+            .filter(|index| !seed_object_indices.contains(index))
+            .map(|index| {
+                (
+                    offset_of(&object_points[index]),
+                    distance(&object_points[index], &center),
+                )
+            })
+            .filter(|(offset, center_distance)| {
+                let in_plane_distance = offset.iter().map(|coordinate| coordinate * coordinate).sum::<f64>();
+                !is_planar || center_distance.powi(2) - in_plane_distance <= eigenvalues[0] * PLANAR_SEED_TOLERANCE
+            })
+            .map(|(offset, _)| offset)
+            .collect();
+
+        SeedFilter {
+            seed_offsets,
+            other_offsets,
+            weights,
+            camera_intrinsics: camera_intrinsics.clone(),
+        }
+    }
+
+    fn is_plausible(&self, image_points: &[SafePoint2D], seed_assignment: &[usize]) -> bool {
+        let seed_size = seed_assignment.len() as f64;
+        let image_center = seed_assignment.iter().fold((0.0, 0.0), |(x, y), index| {
+            (
+                x + image_points[*index].x / seed_size,
+                y + image_points[*index].y / seed_size,
+            )
+        });
+        let spread: f64 = seed_assignment
+            .iter()
+            .map(|index| {
+                (image_points[*index].x - image_center.0).powi(2) + (image_points[*index].y - image_center.1).powi(2)
+            })
+            .sum();
+
+        if spread.is_nan() || spread <= 0.0 {
+            return false;
+        }
+
+        let predicted_points = if self.seed_offsets[0].len() == 3 {
+            self.affine_predictions(image_points, seed_assignment, image_center)
+        } else {
+            self.homography_predictions(image_points, seed_assignment)
+        };
+        let gate = (spread / seed_size).sqrt() * SEED_PREDICTION_TOLERANCE;
+
+        predicted_points
+            .map(|predicted_points| {
+                predicted_points.iter().all(|(x, y)| {
+                    image_points
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, _)| !seed_assignment.contains(index))
+                        .any(|(_, image_point)| (image_point.x - x).hypot(image_point.y - y) <= gate)
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Image positions of the other markers under the seed's affine fit, or `None` when the fit is no scaled rotation.
+    fn affine_predictions(
+        &self,
+        image_points: &[SafePoint2D],
+        seed_assignment: &[usize],
+        image_center: (f64, f64),
+    ) -> Option<Vec<(f64, f64)>> {
+        let mut affine = [[0.0; 3]; 2];
+
+        for (index, weight) in seed_assignment.iter().zip(self.weights.iter()) {
+            for (col, weight) in weight.iter().enumerate() {
+                affine[0][col] += (image_points[*index].x - image_center.0) * weight;
+                affine[1][col] += (image_points[*index].y - image_center.1) * weight;
+            }
+        }
+
+        // Singular values of A from the eigenvalues of A Aᵀ
+        let dot = |a: &[f64; 3], b: &[f64; 3]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+        let (xx, xy, yy) = (
+            dot(&affine[0], &affine[0]),
+            dot(&affine[0], &affine[1]),
+            dot(&affine[1], &affine[1]),
+        );
+        let half_trace = (xx + yy) / 2.0;
+        let deviation = ((xx - yy).powi(2) / 4.0 + xy * xy).sqrt();
+
+        if half_trace - deviation < (half_trace + deviation) / SEED_SCALE_TOLERANCE.powi(2) {
+            return None;
+        }
+
+        Some(
+            self.other_offsets
+                .iter()
+                .map(|offset| {
+                    (
+                        image_center.0 + affine[0].iter().zip(offset).map(|(a, q)| a * q).sum::<f64>(),
+                        image_center.1 + affine[1].iter().zip(offset).map(|(a, q)| a * q).sum::<f64>(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Image positions of the other in-plane markers under the seed's homography, or `None` when the homography
+    /// puts the seed behind the camera or does not come from a rotation.
+    fn homography_predictions(
+        &self,
+        image_points: &[SafePoint2D],
+        seed_assignment: &[usize],
+    ) -> Option<Vec<(f64, f64)>> {
+        let camera_intrinsics = &self.camera_intrinsics;
+        let mut matrix = Vec::with_capacity(2 * SEED_SIZE);
+        let mut rhs = Vec::with_capacity(2 * SEED_SIZE);
+
+        // Direct linear transform with h33 = 1, from plane offsets to normalized image coordinates
+        for (index, offset) in seed_assignment.iter().zip(self.seed_offsets.iter()) {
+            let x = (image_points[*index].x - camera_intrinsics.cx) / camera_intrinsics.fx;
+            let y = (image_points[*index].y - camera_intrinsics.cy) / camera_intrinsics.fy;
+            let (u, v) = (offset[0], offset[1]);
+            matrix.push(vec![u, v, 1.0, 0.0, 0.0, 0.0, -x * u, -x * v]);
+            matrix.push(vec![0.0, 0.0, 0.0, u, v, 1.0, -y * u, -y * v]);
+            rhs.push(x);
+            rhs.push(y);
+        }
+
+        let h = solve_linear_system(matrix, rhs)?;
+        let project = |offset: &[f64]| {
+            let depth = h[6] * offset[0] + h[7] * offset[1] + 1.0;
+            (
+                (h[0] * offset[0] + h[1] * offset[1] + h[2]) / depth,
+                (h[3] * offset[0] + h[4] * offset[1] + h[5]) / depth,
+                depth,
+            )
+        };
+
+        // With h33 = 1 the homography is scaled by the inverse centroid depth, so every seed depth must be positive
+        if self.seed_offsets.iter().any(|offset| project(offset).2 <= 0.0) {
+            return None;
+        }
+
+        let first_column = [h[0], h[3], h[6]];
+        let second_column = [h[1], h[4], h[7]];
+        let dot = |a: &[f64; 3], b: &[f64; 3]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+        let (first_norm, second_norm) = (
+            dot(&first_column, &first_column).sqrt(),
+            dot(&second_column, &second_column).sqrt(),
+        );
+
+        if (first_norm - second_norm).abs() > SEED_ROTATION_TOLERANCE * first_norm.max(second_norm)
+            || dot(&first_column, &second_column).abs() > SEED_ROTATION_TOLERANCE * first_norm * second_norm
+        {
+            return None;
+        }
+
+        Some(
+            self.other_offsets
+                .iter()
+                .map(|offset| {
+                    let (x, y, _) = project(offset);
+                    (
+                        x * camera_intrinsics.fx + camera_intrinsics.cx,
+                        y * camera_intrinsics.fy + camera_intrinsics.cy,
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Gaussian elimination with partial pivoting, `None` for a singular system.
+fn solve_linear_system(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let size = rhs.len();
+
+    for col in 0..size {
+        let pivot = (col..size).max_by(|left, right| {
+            matrix[*left][col]
+                .abs()
+                .partial_cmp(&matrix[*right][col].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+
+        if matrix[pivot][col].is_nan() || matrix[pivot][col].abs() <= f64::EPSILON {
+            return None;
+        }
+
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+
+        let (upper_rows, lower_rows) = matrix.split_at_mut(col + 1);
+        let pivot_row = &upper_rows[col];
+
+        for (offset, row) in lower_rows.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+
+            for (value, pivot_value) in row[col..].iter_mut().zip(pivot_row[col..].iter()) {
+                *value -= factor * pivot_value;
+            }
+
+            rhs[col + 1 + offset] -= factor * rhs[col];
+        }
+    }
+
+    let mut solution = vec![0.0; size];
+
+    for row in (0..size).rev() {
+        let known: f64 = (row + 1..size).map(|col| matrix[row][col] * solution[col]).sum();
+        solution[row] = (rhs[row] - known) / matrix[row][row];
+    }
+
+    Some(solution)
+}
+
+/// Extends a seed hypothesis to every marker. Each marker outside the seed takes the nearest free image point to its
+/// projection; `None` when any marker, seed included, lands further than `gate` from its image point.
+fn complete_assignment(
+    seed_object_indices: &[usize],
+    seed_assignment: &[usize],
+    projected_points: &[SafePoint2D],
+    image_points: &[SafePoint2D],
+    gate: f64,
+) -> Option<Vec<usize>> {
+    let point_distance = |a: &SafePoint2D, b: &SafePoint2D| (a.x - b.x).hypot(a.y - b.y);
+    let mut assignment = vec![None; projected_points.len()];
+    let mut is_used = vec![false; image_points.len()];
+
+    for (object_index, image_index) in seed_object_indices.iter().zip(seed_assignment) {
+        if point_distance(&projected_points[*object_index], &image_points[*image_index]) > gate {
+            return None;
+        }
+
+        assignment[*object_index] = Some(*image_index);
+        is_used[*image_index] = true;
+    }
+
+    for (object_index, projected_point) in projected_points.iter().enumerate() {
+        if assignment[object_index].is_some() {
+            continue;
+        }
+
+        let (image_index, nearest_distance) = image_points
+            .iter()
+            .enumerate()
+            .filter(|(image_index, _)| !is_used[*image_index])
+            .map(|(image_index, image_point)| (image_index, point_distance(projected_point, image_point)))
+            .min_by(|left, right| left.1.total_cmp(&right.1))?;
+
+        if nearest_distance > gate {
+            return None;
+        }
+
+        assignment[object_index] = Some(image_index);
+        is_used[image_index] = true;
+    }
+
+    assignment.into_iter().collect()
+}
+
+fn is_ambiguous(best_rms_reprojection_error: f64, runner_up: Option<&RunnerUpAssignment>) -> bool {
+    runner_up
+        .map(|runner_up| {
+            runner_up.rms_reprojection_error <= best_rms_reprojection_error.max(AMBIGUITY_ERROR_FLOOR) * AMBIGUITY_RATIO
+        })
+        .unwrap_or(false)
+}
+
+/// Matches unordered image points to the solver's marker constellation by hypothesize and verify. Four well spread
+/// markers are tried against every ordered choice of image points that `SeedFilter` finds plausible, with the cheap
+/// `AP3P` solver; a hypothesis survives when its pose is in front of the camera and projects every marker near a free
+/// image point. Each distinct surviving assignment is refined over all its markers, and the one with the lowest RMS
+/// reprojection error wins; only that one gets a full `pose_estimate`. Extra image points are left unassigned, so a
+/// frame may hold stray blobs. Like `compute_pose`, the image points must already be prepared for the solver.
+pub fn solve_correspondence(
+    pose_solver: &PoseSolver,
+    image_points: &[SafePoint2D],
+) -> Result<Correspondence, PoseSolverError> {
+    let object_point_count = pose_solver.object_points().len();
+
+    if image_points.len() < object_point_count {
+        return Err(PoseSolverError::ImagePointCountMismatch {
+            expected: object_point_count,
+            actual: image_points.len(),
+        });
+    }
+
+    let seed_object_indices = seed_object_indices(pose_solver.object_points());
+
+    match assignment_count(image_points.len(), seed_object_indices.len()) {
+        Some(assignments) if assignments <= MAX_SEED_ASSIGNMENTS => {}
+        assignments => {
+            return Err(PoseSolverError::TooManyAssignments {
+                assignments: assignments.unwrap_or(usize::MAX),
+                limit: MAX_SEED_ASSIGNMENTS,
+            })
+        }
+    }
+
+    let seed_filter = SeedFilter::new(
+        pose_solver.camera_intrinsics(),
+        pose_solver.object_points(),
+        &seed_object_indices,
+    );
+    let gate = pose_solver.pose_solver_config().tracking_reprojection_threshold * HYPOTHESIS_GATE_FACTOR;
+    let mut evaluated_assignments = HashSet::new();
+    let mut best: Option<(Vec<usize>, Extrinsics, f64)> = None;
+    let mut runner_up: Option<RunnerUpAssignment> = None;
+
+    for seed_assignment in Assignments::new(image_points.len(), seed_object_indices.len()) {
+        let seed_image_points: Vec<SafePoint2D> = seed_assignment
+            .iter()
+            .map(|index| image_points[*index].clone())
+            .collect();
+
+        if !seed_filter.is_plausible(image_points, &seed_assignment) {
+            continue;
+        }
+
+        // Degenerate seeds such as collinear image points may fail inside OpenCV, they are just not a hypothesis
+        let seed_extrinsics = match pose_solver.solve_minimal_extrinsics(&seed_object_indices, &seed_image_points) {
+            Ok(Some(seed_extrinsics)) if seed_extrinsics.translation_vector[2] > 0.0 => seed_extrinsics,
+            _ => continue,
+        };
+        let projected_points = pose_solver.project_object_points(&seed_extrinsics)?;
+        let assignment = match complete_assignment(
+            &seed_object_indices,
+            &seed_assignment,
+            &projected_points,
+            image_points,
+            gate,
+        ) {
+            Some(assignment) => assignment,
+            None => continue,
+        };
+
+        if !evaluated_assignments.insert(assignment.clone()) {
+            continue;
+        }
+
+        let assigned_image_points: Vec<SafePoint2D> =
+            assignment.iter().map(|index| image_points[*index].clone()).collect();
+        let extrinsics = match pose_solver.solve_extrinsics(&assigned_image_points, Some(&seed_extrinsics)) {
+            Ok((extrinsics, _)) if extrinsics.translation_vector[2] > 0.0 => extrinsics,
+            Ok(_) | Err(PoseSolverError::NoSolution) => continue,
+            Err(error) => return Err(error),
+        };
+        let reprojection_errors: Vec<f64> = pose_solver
+            .project_object_points(&extrinsics)?
+            .iter()
+            .zip(assigned_image_points.iter())
+            .map(|(projected_point, image_point)| {
+                (projected_point.x - image_point.x).hypot(projected_point.y - image_point.y)
+            })
+            .collect();
+        let rms_reprojection_error = rms(&reprojection_errors);
+
+        match &best {
+            Some((_, _, best_rms_reprojection_error)) if *best_rms_reprojection_error <= rms_reprojection_error => {
+                if runner_up
+                    .as_ref()
+                    .map(|runner_up| rms_reprojection_error < runner_up.rms_reprojection_error)
+                    .unwrap_or(true)
+                {
+                    runner_up = Some(RunnerUpAssignment {
+                        assignment,
+                        rms_reprojection_error,
+                    });
+                }
+            }
+            _ => {
+                runner_up = best
+                    .take()
+                    .map(|(assignment, _, rms_reprojection_error)| RunnerUpAssignment {
+                        assignment,
+                        rms_reprojection_error,
+                    });
+                best = Some((assignment, extrinsics, rms_reprojection_error));
+            }
+        }
+    }
+
+    let (assignment, extrinsics, rms_reprojection_error) = best.ok_or(PoseSolverError::NoSolution)?;
+    let assigned_image_points: Vec<SafePoint2D> = assignment.iter().map(|index| image_points[*index].clone()).collect();
+    let pose_estimate =
+        pose_solver.pose_estimate(&extrinsics, &assigned_image_points, (0..assignment.len()).collect())?;
+    let is_ambiguous = is_ambiguous(rms_reprojection_error, runner_up.as_ref());
+
+    if let Some(runner_up) = runner_up.as_ref().filter(|_| is_ambiguous) {
+        debug!(
+            "Ambiguous marker assignment: {:?} at {:.3}px, {:?} at {:.3}px",
+            assignment, rms_reprojection_error, runner_up.assignment, runner_up.rms_reprojection_error
+        );
+    }
+
+    Ok(Correspondence {
+        assignment,
+        pose_estimate,
+        rms_reprojection_error,
+        runner_up,
+        is_ambiguous,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_pinhole;
+    use mcslib_common::types::default_marker_constellation;
+    use std::time::{Duration, Instant};
+
+    fn camera_intrinsics() -> CameraIntrinsics {
+        CameraIntrinsics {
+            fx: 800.0,
+            fy: 800.0,
+            cx: 640.0,
+            cy: 400.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn assignments_are_generated_lazily_and_completed_from_a_seed() {
+        assert_eq!(assignment_count(4, 4), Some(24));
+        assert_eq!(assignment_count(6, 4), Some(360));
+        assert_eq!(assignment_count(3, 4), Some(0));
+        assert_eq!(assignment_count(100, 40), None);
+
+        let assignments: Vec<Vec<usize>> = Assignments::new(3, 2).collect();
+        assert_eq!(
+            assignments,
+            vec![vec![0, 1], vec![0, 2], vec![1, 0], vec![1, 2], vec![2, 0], vec![2, 1]]
+        );
+        assert_eq!(Assignments::new(6, 4).count(), 360);
+        assert_eq!(Assignments::new(3, 4).count(), 0);
+
+        let mut object_points = default_marker_constellation();
+        object_points.push(SafePoint3D::new(0.0, 0.0, 1.0));
+        let seed = seed_object_indices(&object_points);
+        assert_eq!(seed.len(), SEED_SIZE);
+        assert!(!seed.contains(&4));
+
+        let projected_points = vec![
+            SafePoint2D { x: 10.0, y: 10.0 },
+            SafePoint2D { x: 20.0, y: 10.0 },
+            SafePoint2D { x: 20.0, y: 20.0 },
+            SafePoint2D { x: 10.0, y: 20.0 },
+            SafePoint2D { x: 15.0, y: 15.0 },
+        ];
+        let image_points = vec![
+            SafePoint2D { x: 15.5, y: 14.5 },
+            SafePoint2D { x: 50.0, y: 50.0 },
+            SafePoint2D { x: 10.5, y: 10.0 },
+            SafePoint2D { x: 20.0, y: 9.5 },
+            SafePoint2D { x: 19.5, y: 20.0 },
+            SafePoint2D { x: 10.0, y: 20.5 },
+        ];
+        let seed_object_indices = [0, 1, 2, 3];
+        assert_eq!(
+            complete_assignment(
+                &seed_object_indices,
+                &[2, 3, 4, 5],
+                &projected_points,
+                &image_points,
+                2.0
+            ),
+            Some(vec![2, 3, 4, 5, 0])
+        );
+        assert_eq!(
+            complete_assignment(
+                &seed_object_indices,
+                &[2, 3, 4, 1],
+                &projected_points,
+                &image_points,
+                2.0
+            ),
+            None
+        );
+        assert_eq!(
+            complete_assignment(
+                &seed_object_indices,
+                &[2, 3, 4, 5],
+                &projected_points,
+                &image_points,
+                0.6
+            ),
+            None
+        );
+
+        let runner_up = RunnerUpAssignment {
+            assignment: vec![1, 0],
+            rms_reprojection_error: 0.15,
+        };
+        assert!(is_ambiguous(0.01, Some(&runner_up)));
+        assert!(!is_ambiguous(0.01, None));
+        assert!(!is_ambiguous(
+            0.5,
+            Some(&RunnerUpAssignment {
+                rms_reprojection_error: 3.0,
+                ..runner_up
+            })
+        ));
+
+        let pose_solver = PoseSolver::default();
+        match solve_correspondence(&pose_solver, &[SafePoint2D::default()]) {
+            Err(PoseSolverError::ImagePointCountMismatch { expected: 4, actual: 1 }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        match solve_correspondence(&pose_solver, &vec![SafePoint2D::default(); 20]) {
+            Err(PoseSolverError::TooManyAssignments {
+                assignments: 116_280, ..
+            }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    fn asymmetric_constellation() -> Vec<SafePoint3D> {
+        vec![
+            SafePoint3D::new(0.0, 0.0, 0.0),
+            SafePoint3D::new(40.0, 0.0, 0.0),
+            SafePoint3D::new(0.0, 25.0, 0.0),
+            SafePoint3D::new(10.0, 10.0, 20.0),
+            SafePoint3D::new(-15.0, 30.0, 5.0),
+        ]
+    }
+
+    /// Twelve image points: the asymmetric constellation under `extrinsics`, then two square trackers and a stray blob.
+    fn crowded_frame(extrinsics: &Extrinsics) -> Vec<SafePoint2D> {
+        let camera_intrinsics = camera_intrinsics();
+        let mut image_points = project_pinhole(&camera_intrinsics, &asymmetric_constellation(), extrinsics);
+
+        for translation_vector in &[[-120.0, 60.0, 450.0], [150.0, -40.0, 380.0]] {
+            let square_extrinsics = Extrinsics {
+                rotation_vector: [1.2, 0.3, 0.0],
+                translation_vector: *translation_vector,
+            };
+            image_points.extend(project_pinhole(
+                &camera_intrinsics,
+                &default_marker_constellation(),
+                &square_extrinsics,
+            ));
+        }
+
+        image_points.push(SafePoint2D { x: 900.0, y: 650.0 });
+        image_points.push(SafePoint2D { x: 200.0, y: 120.0 });
+        image_points.push(SafePoint2D { x: 660.0, y: 380.0 });
+        image_points.truncate(12);
+        image_points
+    }
+
+    #[test]
+    fn seed_filter_keeps_true_seeds_and_prunes_most_others() {
+        let image_points = crowded_frame(&Extrinsics {
+            rotation_vector: [0.3, -0.2, 0.1],
+            translation_vector: [5.0, -10.0, 300.0],
+        });
+
+        // The asymmetric constellation spans 3D, the square is planar; both appear in the crowded frame
+        for (object_points, first_image_index) in
+            &[(asymmetric_constellation(), 0), (default_marker_constellation(), 5)]
+        {
+            let seed_object_indices = seed_object_indices(object_points);
+            let seed_filter = SeedFilter::new(&camera_intrinsics(), object_points, &seed_object_indices);
+
+            for (rotation_vector, depth) in &[
+                ([0.0, 0.0, 0.0], 300.0),
+                ([0.3, -0.2, 0.1], 250.0),
+                ([1.1, 0.0, 0.0], 400.0),
+                ([0.0, -1.2, 0.5], 150.0),
+                ([2.5, 0.4, -0.3], 600.0),
+            ] {
+                let extrinsics = Extrinsics {
+                    rotation_vector: *rotation_vector,
+                    translation_vector: [10.0, -5.0, *depth],
+                };
+                let projected_points = project_pinhole(&camera_intrinsics(), object_points, &extrinsics);
+                assert!(
+                    seed_filter.is_plausible(&projected_points, &seed_object_indices),
+                    "{:?}",
+                    rotation_vector
+                );
+            }
+
+            let true_seed: Vec<usize> = seed_object_indices
+                .iter()
+                .map(|index| first_image_index + index)
+                .collect();
+            assert!(seed_filter.is_plausible(&image_points, &true_seed));
+
+            let plausible_seeds = Assignments::new(image_points.len(), SEED_SIZE)
+                .filter(|seed_assignment| seed_filter.is_plausible(&image_points, seed_assignment))
+                .count();
+            assert!(plausible_seeds < assignment_count(12, SEED_SIZE).unwrap() / 100);
+        }
+    }
+
+    #[test]
+    fn correspondence_in_a_crowded_frame_is_fast() {
+        let pose_solver = PoseSolver::new(camera_intrinsics(), asymmetric_constellation(), Default::default()).unwrap();
+        let image_points = crowded_frame(&Extrinsics {
+            rotation_vector: [0.3, -0.2, 0.1],
+            translation_vector: [5.0, -10.0, 300.0],
+        });
+
+        let start = Instant::now();
+        let correspondence = solve_correspondence(&pose_solver, &image_points).unwrap();
+        let elapsed = start.elapsed();
+        assert_eq!(correspondence.assignment, vec![0, 1, 2, 3, 4]);
+        assert!(!correspondence.is_ambiguous);
+        assert!(elapsed < Duration::from_millis(200), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn correspondence_recovers_shuffled_markers() {
+        let object_points = vec![
+            SafePoint3D::new(0.0, 0.0, 0.0),
+            SafePoint3D::new(40.0, 0.0, 0.0),
+            SafePoint3D::new(0.0, 25.0, 0.0),
+            SafePoint3D::new(10.0, 10.0, 20.0),
+            SafePoint3D::new(-15.0, 30.0, 5.0),
+        ];
+        let pose_solver = PoseSolver::new(camera_intrinsics(), object_points.clone(), Default::default()).unwrap();
+        let extrinsics = Extrinsics {
+            rotation_vector: [0.3, -0.2, 0.1],
+            translation_vector: [5.0, -10.0, 300.0],
+        };
+        let projected_points = project_pinhole(pose_solver.camera_intrinsics(), &object_points, &extrinsics);
+        // image_points[i] = projected_points[shuffle[i]], plus a stray blob
+        let shuffle = [3, 0, 4, 1, 2];
+        let mut image_points: Vec<SafePoint2D> = shuffle.iter().map(|index| projected_points[*index].clone()).collect();
+        image_points.push(SafePoint2D { x: 100.0, y: 700.0 });
+
+        let correspondence = solve_correspondence(&pose_solver, &image_points).unwrap();
+        assert_eq!(correspondence.assignment, vec![1, 3, 4, 0, 2]);
+        assert!(correspondence.rms_reprojection_error < 1e-3);
+        assert!(!correspondence.is_ambiguous);
+
+        // The default constellation is a square, so its rotations fit the image points as well
+        let square_solver =
+            PoseSolver::new(camera_intrinsics(), default_marker_constellation(), Default::default()).unwrap();
+        let square_points = project_pinhole(
+            square_solver.camera_intrinsics(),
+            square_solver.object_points(),
+            &extrinsics,
+        );
+        let square_points = vec![
+            square_points[2].clone(),
+            square_points[0].clone(),
+            square_points[3].clone(),
+            square_points[1].clone(),
+        ];
+        let correspondence = solve_correspondence(&square_solver, &square_points).unwrap();
+        assert!(correspondence.is_ambiguous);
+        assert!(correspondence.runner_up.unwrap().rms_reprojection_error < 1e-3);
+    }
+}
@@ -0,0 +1,469 @@
+use crate::motion_tracker_compute::rms;
+use crate::{mat_from_rows, new_f64_mat};
+use mcslib_common::types::{CameraCalibration, CameraIntrinsics, DistortionModel, ImageResidual, SafePoint3D};
+use opencv::calib3d::{
+    calibrate_camera, find_chessboard_corners, project_points, CALIB_CB_ADAPTIVE_THRESH, CALIB_CB_NORMALIZE_IMAGE,
+};
+use opencv::core::{Mat, Point2f, Point3f, Size, TermCriteria, TermCriteria_COUNT, TermCriteria_EPS};
+use opencv::imgcodecs::{imread, IMREAD_GRAYSCALE};
+use opencv::imgproc::corner_sub_pix;
+use opencv::prelude::Vector;
+use opencv::types::{VectorOfMat, VectorOfPoint2f, VectorOfPoint3f, VectorOfVectorOfPoint2f, VectorOfVectorOfPoint3f};
+use opencv::Error as OpenCVError;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FormatterResult};
+use std::fs::read_dir;
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+
+/// Fewer detected views leave the distortion coefficients poorly constrained.
+pub const MIN_CALIBRATION_IMAGES: usize = 3;
+const CALIBRATION_IMAGE_EXTENSIONS: &[&str] = &["bmp", "jpeg", "jpg", "pgm", "png", "tif", "tiff"];
+const SUB_PIXEL_WINDOW_SIZE: i32 = 11;
+const TERMINATION_MAX_COUNT: i32 = 30;
+const TERMINATION_EPSILON: f64 = 0.001;
+
+/// Planar chessboard target. Corners are counted between squares, so an 8x8 board has 7x7 inner corners, and the
+/// square size sets the length unit of the calibration.
+#[derive(Debug, Clone, Copy)]
+pub struct ChessboardPattern {
+    pub inner_corners_per_row: usize,
+    pub inner_corners_per_column: usize,
+    pub square_size: f64,
+}
+
+#[derive(Debug)]
+pub enum IntrinsicCalibrationError {
+    IO(IOError),
+    ImageSizeMismatch(String),
+    NotEnoughDetections { detected: usize, required: usize },
+    OpenCV(OpenCVError),
+}
+
+impl Display for IntrinsicCalibrationError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            IntrinsicCalibrationError::IO(error) => write!(formatter, "IO error: {}", error),
+            IntrinsicCalibrationError::ImageSizeMismatch(image_name) => write!(
+                formatter,
+                "Image \"{}\" differs in size from the other calibration images",
+                image_name
+            ),
+            IntrinsicCalibrationError::NotEnoughDetections { detected, required } => write!(
+                formatter,
+                "Calibration pattern found in {} image(s), at least {} needed",
+                detected, required
+            ),
+            IntrinsicCalibrationError::OpenCV(error) => write!(formatter, "OpenCV error: {}", error),
+        }
+    }
+}
+
+impl Error for IntrinsicCalibrationError {}
+
+impl From<IOError> for IntrinsicCalibrationError {
+    fn from(error: IOError) -> IntrinsicCalibrationError {
+        IntrinsicCalibrationError::IO(error)
+    }
+}
+
+impl From<OpenCVError> for IntrinsicCalibrationError {
+    fn from(error: OpenCVError) -> IntrinsicCalibrationError {
+        IntrinsicCalibrationError::OpenCV(error)
+    }
+}
+
+impl ChessboardPattern {
+    fn pattern_size(&self) -> Size {
+        Size::new(self.inner_corners_per_row as i32, self.inner_corners_per_column as i32)
+    }
+
+    /// Inner corners on the Z=0 plane, row by row, in the order `find_chessboard_corners` reports them.
+    pub fn object_points(&self) -> Vec<SafePoint3D> {
+        (0..self.inner_corners_per_column)
+            .flat_map(|row| {
+                (0..self.inner_corners_per_row)
+                    .map(move |col| SafePoint3D::new(col as f64 * self.square_size, row as f64 * self.square_size, 0.0))
+            })
+            .collect()
+    }
+}
+
+fn to_vector_of_point3f(points: &[SafePoint3D]) -> VectorOfPoint3f {
+    let mut vector = VectorOfPoint3f::with_capacity(points.len());
+
+    for point in points {
+        vector.push(Point3f::new(point.x as f32, point.y as f32, point.z as f32));
+    }
+
+    vector
+}
+
+fn is_calibration_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+        .filter(|extension| CALIBRATION_IMAGE_EXTENSIONS.contains(&extension.as_str()))
+        .is_some()
+}
+
+/// Calibrates from every image in `directory`, read as grayscale in file name order. Files that cannot be read are
+/// reported in `unreadable_images`.
+pub fn calibrate_from_directory(
+    directory: &Path,
+    chessboard_pattern: &ChessboardPattern,
+) -> Result<CameraCalibration, IntrinsicCalibrationError> {
+    let mut image_paths: Vec<PathBuf> = read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_calibration_image(path))
+        .collect();
+    image_paths.sort();
+
+    let mut images = Vec::with_capacity(image_paths.len());
+    let mut unreadable_images = vec![];
+
+    for image_path in image_paths {
+        let image = imread(&image_path.to_string_lossy(), IMREAD_GRAYSCALE)?;
+        let image_name = image_path
+            .file_name()
+            .map_or_else(Default::default, |file_name| file_name.to_string_lossy().into_owned());
+
+        if image.empty()? {
+            warn!("Cannot read calibration image \"{}\"", image_name);
+            unreadable_images.push(image_name);
+            continue;
+        }
+
+        images.push((image_name, image));
+    }
+
+    let mut camera_calibration = calibrate_from_images(&images, chessboard_pattern)?;
+    camera_calibration.unreadable_images = unreadable_images;
+
+    Ok(camera_calibration)
+}
+
+/// Runs OpenCV's intrinsic calibration with the `(k1, k2, p1, p2, k3)` distortion model over named grayscale
+/// images of the same size. Images without a complete chessboard are skipped and reported.
+pub fn calibrate_from_images(
+    images: &[(String, Mat)],
+    chessboard_pattern: &ChessboardPattern,
+) -> Result<CameraCalibration, IntrinsicCalibrationError> {
+    let pattern_size = chessboard_pattern.pattern_size();
+    let object_points = chessboard_pattern.object_points();
+    let termination_criteria = TermCriteria::new(
+        TermCriteria_COUNT + TermCriteria_EPS,
+        TERMINATION_MAX_COUNT,
+        TERMINATION_EPSILON,
+    )?;
+    let mut image_size: Option<Size> = None;
+    let mut detected_image_names = vec![];
+    let mut detected_corners = vec![];
+    let mut skipped_images = vec![];
+
+    for (image_name, image) in images {
+        let size = image.size()?;
+
+        match image_size {
+            Some(image_size) if image_size != size => {
+                return Err(IntrinsicCalibrationError::ImageSizeMismatch(image_name.clone()))
+            }
+            _ => image_size = Some(size),
+        }
+
+        let mut corners = VectorOfPoint2f::new();
+
+        if !find_chessboard_corners(
+            image,
+            pattern_size,
+            &mut corners,
+            CALIB_CB_ADAPTIVE_THRESH + CALIB_CB_NORMALIZE_IMAGE,
+        )? {
+            debug!("No chessboard found in \"{}\"", image_name);
+            skipped_images.push(image_name.clone());
+            continue;
+        }
+
+        corner_sub_pix(
+            image,
+            &mut corners,
+            Size::new(SUB_PIXEL_WINDOW_SIZE, SUB_PIXEL_WINDOW_SIZE),
+            Size::new(-1, -1),
+            &termination_criteria,
+        )?;
+        detected_image_names.push(image_name.clone());
+        detected_corners.push(corners.to_vec());
+    }
+
+    if detected_corners.len() < MIN_CALIBRATION_IMAGES {
+        return Err(IntrinsicCalibrationError::NotEnoughDetections {
+            detected: detected_corners.len(),
+            required: MIN_CALIBRATION_IMAGES,
+        });
+    }
+
+    let image_size = image_size.unwrap_or_default();
+    let pattern_points = to_vector_of_point3f(&object_points);
+    let mut all_object_points = VectorOfVectorOfPoint3f::with_capacity(detected_corners.len());
+    let mut all_image_points = VectorOfVectorOfPoint2f::with_capacity(detected_corners.len());
+
+    for corners in &detected_corners {
+        let mut view_image_points = VectorOfPoint2f::with_capacity(corners.len());
+
+        for corner in corners {
+            view_image_points.push(*corner);
+        }
+
+        all_object_points.push(to_vector_of_point3f(&object_points));
+        all_image_points.push(view_image_points);
+    }
+
+    let mut camera_matrix = new_f64_mat(3, 3)?;
+    let mut dist_coeffs = new_f64_mat(5, 1)?;
+    let mut rotation_vectors = VectorOfMat::new();
+    let mut translation_vectors = VectorOfMat::new();
+    let rms_reprojection_error = calibrate_camera(
+        &all_object_points,
+        &all_image_points,
+        image_size,
+        &mut camera_matrix,
+        &mut dist_coeffs,
+        &mut rotation_vectors,
+        &mut translation_vectors,
+        0,
+        &termination_criteria,
+    )?;
+
+    let camera_intrinsics = CameraIntrinsics {
+        fx: *camera_matrix.at_2d::<f64>(0, 0)?,
+        fy: *camera_matrix.at_2d::<f64>(1, 1)?,
+        cx: *camera_matrix.at_2d::<f64>(0, 2)?,
+        cy: *camera_matrix.at_2d::<f64>(1, 2)?,
+        distortion_coefficients: [
+            *dist_coeffs.at::<f64>(0)?,
+            *dist_coeffs.at::<f64>(1)?,
+            *dist_coeffs.at::<f64>(2)?,
+            *dist_coeffs.at::<f64>(3)?,
+            *dist_coeffs.at::<f64>(4)?,
+        ],
+        distortion_model: DistortionModel::RadialTangential,
+    };
+    let camera_matrix = mat_from_rows(&camera_intrinsics.camera_matrix())?;
+    let mut image_residuals = Vec::with_capacity(detected_corners.len());
+
+    for (index, (image_name, corners)) in detected_image_names.iter().zip(detected_corners.iter()).enumerate() {
+        let mut projected_points = VectorOfPoint2f::new();
+        let mut jacobian = Mat::default()?;
+        project_points(
+            &pattern_points,
+            &rotation_vectors.get(index)?,
+            &translation_vectors.get(index)?,
+            &camera_matrix,
+            &dist_coeffs,
+            &mut projected_points,
+            &mut jacobian,
+            0.0,
+        )?;
+
+        let reprojection_errors: Vec<f64> = projected_points
+            .to_vec()
+            .iter()
+            .zip(corners.iter())
+            .map(|(projected_point, corner): (&Point2f, &Point2f)| {
+                f64::from(projected_point.x - corner.x).hypot(f64::from(projected_point.y - corner.y))
+            })
+            .collect();
+
+        image_residuals.push(ImageResidual {
+            image_name: image_name.clone(),
+            rms_reprojection_error: rms(&reprojection_errors),
+        });
+    }
+
+    Ok(CameraCalibration {
+        camera_intrinsics,
+        image_width: image_size.width as u32,
+        image_height: image_size.height as u32,
+        rms_reprojection_error,
+        image_residuals,
+        skipped_images,
+        unreadable_images: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mat_from_gray_buffer;
+    use mcslib_common::geometry::{rotate, rotation_matrix_from_rotation_vector};
+    use std::env::temp_dir;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    const IMAGE_WIDTH: usize = 640;
+    const IMAGE_HEIGHT: usize = 480;
+    const SUPERSAMPLING: usize = 4;
+
+    fn camera_intrinsics() -> CameraIntrinsics {
+        CameraIntrinsics {
+            fx: 600.0,
+            fy: 590.0,
+            cx: 322.0,
+            cy: 236.0,
+            ..Default::default()
+        }
+    }
+
+    /// Renders the chessboard, with a white border one square wide, as a distortion-free camera sees it with the board
+    /// rotated by `rotation_vector` about its center at `distance` straight ahead. Each pixel averages a grid of
+    /// samples, so the corner edges are anti-aliased like a real capture.
+    fn render_chessboard(chessboard_pattern: &ChessboardPattern, rotation_vector: [f64; 3], distance: f64) -> Vec<u8> {
+        let camera_intrinsics = camera_intrinsics();
+        let square_size = chessboard_pattern.square_size;
+        let columns = chessboard_pattern.inner_corners_per_row as f64 + 1.0;
+        let rows = chessboard_pattern.inner_corners_per_column as f64 + 1.0;
+        let rotation_matrix = rotation_matrix_from_rotation_vector(&rotation_vector);
+        let axis_x = rotate(&rotation_matrix, &SafePoint3D::new(1.0, 0.0, 0.0));
+        let axis_y = rotate(&rotation_matrix, &SafePoint3D::new(0.0, 1.0, 0.0));
+        let board_center = rotate(
+            &rotation_matrix,
+            &SafePoint3D::new(
+                (columns / 2.0 - 1.0) * square_size,
+                (rows / 2.0 - 1.0) * square_size,
+                0.0,
+            ),
+        );
+        let origin = [-board_center.x, -board_center.y, distance - board_center.z];
+        let determinant = |a: [f64; 3], b: [f64; 3], c: [f64; 3]| {
+            a[0] * (b[1] * c[2] - b[2] * c[1]) - b[0] * (a[1] * c[2] - a[2] * c[1]) + c[0] * (a[1] * b[2] - a[2] * b[1])
+        };
+        let axis_x = [axis_x.x, axis_x.y, axis_x.z];
+        let axis_y = [axis_y.x, axis_y.y, axis_y.z];
+        let target = [-origin[0], -origin[1], -origin[2]];
+        let mut image = vec![0u8; IMAGE_WIDTH * IMAGE_HEIGHT];
+
+        for (index, pixel) in image.iter_mut().enumerate() {
+            let mut intensity = 0.0;
+
+            for sample in 0..SUPERSAMPLING * SUPERSAMPLING {
+                let u =
+                    (index % IMAGE_WIDTH) as f64 + ((sample % SUPERSAMPLING) as f64 + 0.5) / SUPERSAMPLING as f64 - 0.5;
+                let v =
+                    (index / IMAGE_WIDTH) as f64 + ((sample / SUPERSAMPLING) as f64 + 0.5) / SUPERSAMPLING as f64 - 0.5;
+                let ray = [
+                    -(u - camera_intrinsics.cx) / camera_intrinsics.fx,
+                    -(v - camera_intrinsics.cy) / camera_intrinsics.fy,
+                    -1.0,
+                ];
+                // Cramer's rule on x * axis_x + y * axis_y + depth * ray = -origin, the ray pointing back at the camera
+                let denominator = determinant(axis_x, axis_y, ray);
+                let x = determinant(target, axis_y, ray) / denominator / square_size;
+                let y = determinant(axis_x, target, ray) / denominator / square_size;
+
+                intensity += if x < -2.0 || x > columns || y < -2.0 || y > rows {
+                    128.0
+                } else if x < -1.0 || x > columns - 1.0 || y < -1.0 || y > rows - 1.0 {
+                    255.0
+                } else if (x.floor() + y.floor()) as i64 % 2 == 0 {
+                    0.0
+                } else {
+                    255.0
+                };
+            }
+
+            *pixel = (intensity / (SUPERSAMPLING * SUPERSAMPLING) as f64).round() as u8;
+        }
+
+        image
+    }
+
+    fn rendered_views(chessboard_pattern: &ChessboardPattern) -> Vec<(String, Vec<u8>)> {
+        [
+            ([0.3, 0.0, 0.0], 420.0),
+            ([0.0, 0.35, 0.0], 400.0),
+            ([-0.25, 0.2, 0.1], 450.0),
+            ([0.2, -0.3, -0.1], 430.0),
+            ([0.05, 0.1, 0.3], 380.0),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(index, (rotation_vector, distance))| {
+            (
+                format!("view_{}.pgm", index),
+                render_chessboard(chessboard_pattern, *rotation_vector, *distance),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn chessboard_object_points_follow_corner_order() {
+        let chessboard_pattern = ChessboardPattern {
+            inner_corners_per_row: 3,
+            inner_corners_per_column: 2,
+            square_size: 25.0,
+        };
+        let object_points = chessboard_pattern.object_points();
+        assert_eq!(object_points.len(), 6);
+        assert!((object_points[2].x - 50.0).abs() < f64::EPSILON && object_points[2].y.abs() < f64::EPSILON);
+        assert!(object_points[3].x.abs() < f64::EPSILON && (object_points[3].y - 25.0).abs() < f64::EPSILON);
+        assert!(object_points.iter().all(|point| point.z.abs() < f64::EPSILON));
+
+        assert!(is_calibration_image(Path::new("captures/board_01.PNG")));
+        assert!(!is_calibration_image(Path::new("captures/notes.txt")));
+    }
+
+    #[test]
+    fn synthetic_views_recover_the_intrinsics() {
+        let chessboard_pattern = ChessboardPattern {
+            inner_corners_per_row: 7,
+            inner_corners_per_column: 6,
+            square_size: 25.0,
+        };
+        let mut views = rendered_views(&chessboard_pattern);
+        views.push(("blank.pgm".into(), vec![128; IMAGE_WIDTH * IMAGE_HEIGHT]));
+
+        let images: Vec<(String, Mat)> = views
+            .iter()
+            .map(|(image_name, buffer)| {
+                (
+                    image_name.clone(),
+                    mat_from_gray_buffer(buffer, IMAGE_WIDTH, IMAGE_HEIGHT).unwrap(),
+                )
+            })
+            .collect();
+
+        let camera_calibration = calibrate_from_images(&images, &chessboard_pattern).unwrap();
+        let expected = camera_intrinsics();
+        let camera_intrinsics = &camera_calibration.camera_intrinsics;
+        assert!((camera_intrinsics.fx - expected.fx).abs() < expected.fx * 0.01);
+        assert!((camera_intrinsics.fy - expected.fy).abs() < expected.fy * 0.01);
+        assert!((camera_intrinsics.cx - expected.cx).abs() < 5.0);
+        assert!((camera_intrinsics.cy - expected.cy).abs() < 5.0);
+        assert!(camera_calibration.rms_reprojection_error < 0.3);
+        assert_eq!(camera_calibration.image_residuals.len(), 5);
+        assert_eq!(camera_calibration.skipped_images, vec!["blank.pgm"]);
+        assert_eq!(
+            (camera_calibration.image_width, camera_calibration.image_height),
+            (640, 480)
+        );
+
+        // Binary PGM files, which OpenCV reads back losslessly, plus one that is not an image at all
+        let directory = temp_dir().join(format!("mcslib-intrinsic-calibration-{}", std::process::id()));
+        create_dir_all(&directory).unwrap();
+
+        for (image_name, buffer) in &views {
+            let mut pgm = format!("P5\n{} {}\n255\n", IMAGE_WIDTH, IMAGE_HEIGHT).into_bytes();
+            pgm.extend_from_slice(buffer);
+            write(directory.join(image_name), pgm).unwrap();
+        }
+
+        write(directory.join("broken.png"), b"not a png").unwrap();
+        let camera_calibration = calibrate_from_directory(&directory, &chessboard_pattern);
+        remove_dir_all(&directory).unwrap();
+
+        let camera_calibration = camera_calibration.unwrap();
+        assert_eq!(camera_calibration.image_residuals.len(), 5);
+        assert_eq!(camera_calibration.skipped_images, vec!["blank.pgm"]);
+        assert_eq!(camera_calibration.unreadable_images, vec!["broken.png"]);
+    }
+}
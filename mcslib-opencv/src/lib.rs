@@ -1,67 +1,106 @@
 pub extern crate opencv;
 
+#[macro_use]
+extern crate mcslib_common;
+
+pub mod blob_detection;
+pub mod correspondence;
+pub mod intrinsic_calibration;
 pub mod motion_tracker_compute;
+pub mod pose_tracker;
+pub mod tracker_identification;
+pub mod undistortion;
+
+pub use motion_tracker_compute::*;
+pub use pose_tracker::*;
 
-use mcslib_common::once_cell::unsync::OnceCell;
-use opencv::core::{DataType, Mat, MatExpr, Point3d};
+use mcslib_common::types::{SafePoint2D, SafePoint3D};
+use opencv::core::{DataType, Mat, Point2d, Point3d, Scalar};
 use opencv::prelude::Vector;
-use opencv::types::VectorOfPoint3d;
-
-pub const CAMERA_WIDTH: i32 = 10;
-pub const CAMERA_RADIUS: f64 = CAMERA_WIDTH as f64 / 2.0;
-pub const FOCAL_LENGTH: f64 = CAMERA_WIDTH as f64;
-pub const CAMERA_MATRIX_ARRAY: [[f64; 3]; 3] = [
-    [FOCAL_LENGTH, 0.0, CAMERA_RADIUS],
-    [FOCAL_LENGTH, CAMERA_RADIUS, 0.0],
-    [0.0, 0.0, 1.0],
-];
-
-static mut INIT_STATE: bool = false;
-static mut CAMERA_MATRIX: OnceCell<Mat> = OnceCell::new();
-static mut DISTORTION_COEFFICIENTS: OnceCell<MatExpr> = OnceCell::new();
-static mut REFERENCE_POINTS: OnceCell<VectorOfPoint3d> = OnceCell::new();
-
-pub fn is_initialized() -> bool {
-    unsafe { INIT_STATE }
+use opencv::types::{VectorOfPoint2d, VectorOfPoint3d};
+use opencv::Error as OpenCVError;
+
+pub(crate) fn new_f64_mat(rows: usize, cols: usize) -> Result<Mat, OpenCVError> {
+    Mat::new_rows_cols_with_default(rows as i32, cols as i32, f64::typ(), Scalar::all(0.0))
 }
 
-pub fn init_globals() {
-    if is_initialized() {
-        return;
+/// Copies a grayscale frame stored row by row without padding into an 8-bit single channel Mat. The dimensions must
+/// fit in an `i32` and match the buffer length.
+pub(crate) fn mat_from_gray_buffer(buffer: &[u8], width: usize, height: usize) -> Result<Mat, OpenCVError> {
+    let mut mat = Mat::new_rows_cols_with_default(height as i32, width as i32, u8::typ(), Scalar::all(0.0))?;
+
+    for (row, pixels) in buffer.chunks(width.max(1)).take(height).enumerate() {
+        mat.at_row_mut::<u8>(row as i32)?.copy_from_slice(pixels);
     }
 
-    unsafe {
-        CAMERA_MATRIX.get_or_init(|| {
-            let mut camera_matrix = Mat::new_rows_cols(3, 3, f64::typ()).unwrap();
+    Ok(mat)
+}
 
-            for row in 0..3 {
-                for col in 0..3 {
-                    *camera_matrix.at_2d_mut(row, col).unwrap() = CAMERA_MATRIX_ARRAY[row as usize][col as usize];
-                }
-            }
+pub(crate) fn mat_from_rows<R: AsRef<[f64]>>(rows: &[R]) -> Result<Mat, OpenCVError> {
+    let cols = rows.first().map_or(0, |row| row.as_ref().len());
+    let mut mat = new_f64_mat(rows.len(), cols)?;
 
-            camera_matrix
-        });
-        DISTORTION_COEFFICIENTS.get_or_init(|| Mat::zeros(4, 1, f64::typ()).unwrap());
-        REFERENCE_POINTS.get_or_init(|| {
-            let mut ref_points = VectorOfPoint3d::new();
-            ref_points.push(Point3d::new(11.4, 0.0, -11.4));
-            ref_points.push(Point3d::new(11.4, 0.0, 11.4));
-            ref_points.push(Point3d::new(-11.4, 0.0, 11.4));
-            ref_points.push(Point3d::new(-11.4, 0.0, -11.4));
-            ref_points
-        });
+    for (row, values) in rows.iter().enumerate() {
+        for (col, value) in values.as_ref().iter().enumerate() {
+            *mat.at_2d_mut::<f64>(row as i32, col as i32)? = *value;
+        }
     }
+
+    Ok(mat)
 }
 
-pub fn get_camera_matrix<'a>() -> &'a Mat {
-    unsafe { CAMERA_MATRIX.get().unwrap() }
+pub(crate) fn mat_from_column(values: &[f64]) -> Result<Mat, OpenCVError> {
+    let mut mat = new_f64_mat(values.len(), 1)?;
+
+    for (row, value) in values.iter().enumerate() {
+        *mat.at_2d_mut::<f64>(row as i32, 0)? = *value;
+    }
+
+    Ok(mat)
 }
 
-pub fn get_distortion_coefficients<'a>() -> &'a MatExpr {
-    unsafe { DISTORTION_COEFFICIENTS.get().unwrap() }
+pub(crate) fn to_vector_of_point3d(points: &[SafePoint3D]) -> VectorOfPoint3d {
+    let mut vector = VectorOfPoint3d::with_capacity(points.len());
+
+    for point in points {
+        vector.push(Point3d::new(point.x, point.y, point.z));
+    }
+
+    vector
+}
+
+pub(crate) fn to_vector_of_point2d(points: &[SafePoint2D]) -> VectorOfPoint2d {
+    let mut vector = VectorOfPoint2d::with_capacity(points.len());
+
+    for point in points {
+        vector.push(Point2d::new(point.x, point.y));
+    }
+
+    vector
 }
 
-pub fn get_object_points<'a>() -> &'a VectorOfPoint3d {
-    unsafe { REFERENCE_POINTS.get().unwrap() }
+/// Ideal pinhole projection of a constellation, to synthesize image points in tests.
+#[cfg(test)]
+pub(crate) fn project_pinhole(
+    camera_intrinsics: &mcslib_common::types::CameraIntrinsics,
+    object_points: &[SafePoint3D],
+    extrinsics: &Extrinsics,
+) -> Vec<SafePoint2D> {
+    use mcslib_common::geometry::{rotate, rotation_matrix_from_rotation_vector};
+
+    let rotation_matrix = rotation_matrix_from_rotation_vector(&extrinsics.rotation_vector);
+
+    object_points
+        .iter()
+        .map(|object_point| {
+            let camera_point = rotate(&rotation_matrix, object_point);
+            let depth = camera_point.z + extrinsics.translation_vector[2];
+            SafePoint2D {
+                x: camera_intrinsics.fx * (camera_point.x + extrinsics.translation_vector[0]) / depth
+                    + camera_intrinsics.cx,
+                y: camera_intrinsics.fy * (camera_point.y + extrinsics.translation_vector[1]) / depth
+                    + camera_intrinsics.cy,
+            }
+        })
+        .collect()
 }
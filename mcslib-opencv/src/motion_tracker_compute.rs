@@ -1,62 +1,638 @@
-use crate::{get_camera_matrix, get_distortion_coefficients, get_object_points};
-use mcslib_common::types::SafePoint2D;
-use opencv::calib3d::{rodrigues, solve_pnp};
-use opencv::core::{no_array, DataType, Mat, Point2d, _OutputArrayTrait};
+use crate::undistortion::{distortion_coefficients_mat, undistort_image_points};
+use crate::{mat_from_column, mat_from_rows, new_f64_mat, to_vector_of_point2d, to_vector_of_point3d};
+use mcslib_common::config::{
+    validate_marker_constellation, validate_pose_solver_config, ConfigLoader, ConfigValidation,
+    MIN_MARKER_CONSTELLATION_POINTS,
+};
+use mcslib_common::geometry::{
+    centroid, distance, pose_from_camera_frame, rotation_matrix_from_rotation_vector, symmetric_eigen,
+};
+use mcslib_common::types::{
+    default_marker_constellation, CameraCalibration, CameraIntrinsics, DistortionModel, PnPMethod, PoseConvention,
+    PoseEstimate, PoseQuality, PoseSolverConfig, SafePoint2D, SafePoint3D, TrackerEndpoint, UndistortionMode,
+};
+use opencv::calib3d::{
+    project_points, solve_pnp, solve_pnp_ransac, SOLVEPNP_AP3P, SOLVEPNP_EPNP, SOLVEPNP_IPPE_SQUARE,
+    SOLVEPNP_ITERATIVE, SOLVEPNP_P3P,
+};
+use opencv::core::{no_array, Mat};
 use opencv::prelude::Vector;
 use opencv::types::VectorOfPoint2d;
 use opencv::Error as OpenCVError;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FormatterResult};
 
-pub fn compute_pose(
-    image_points_array: &[SafePoint2D],
-    translation: &mut [f64; 3],
-    euler_angles: &mut [f64; 3],
-) -> Result<(), OpenCVError> {
-    let object_points_ref = get_object_points();
-    let camera_matrix = get_camera_matrix();
-    let dist_coeffs = get_distortion_coefficients();
+/// Poses whose Jacobian condition number exceeds this are flagged as degenerate. The translation columns are scaled
+/// by the constellation size first, so the threshold does not depend on the length unit.
+pub const DEGENERATE_CONDITION_NUMBER: f64 = 1e8;
+const POSE_PARAMETER_COUNT: usize = 6;
+const ROTATION_PARAMETER_COUNT: usize = 3;
 
-    unsafe {
-        let mut image_points = VectorOfPoint2d::new();
+#[derive(Debug)]
+pub enum PoseSolverError {
+    InvalidCalibration(String),
+    ImagePointCountMismatch { expected: usize, actual: usize },
+    NoSolution,
+    OpenCV(OpenCVError),
+    TooManyAssignments { assignments: usize, limit: usize },
+}
+
+/// Rotation and translation vectors of the marker constellation in the OpenCV camera frame, as `solve_pnp` reports
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extrinsics {
+    pub rotation_vector: [f64; 3],
+    pub translation_vector: [f64; 3],
+}
+
+/// Solves a tracker pose against its own calibration. The calibration is plain data and every call builds its own
+/// OpenCV buffers, so one solver can be shared between threads or cloned per tracker.
+#[derive(Debug, Clone)]
+pub struct PoseSolver {
+    camera_intrinsics: CameraIntrinsics,
+    object_points: Vec<SafePoint3D>,
+    pose_solver_config: PoseSolverConfig,
+    pose_convention: PoseConvention,
+}
+
+impl Display for PoseSolverError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            PoseSolverError::InvalidCalibration(reason) => write!(formatter, "Invalid calibration: {}", reason),
+            PoseSolverError::ImagePointCountMismatch { expected, actual } => write!(
+                formatter,
+                "Expected {} image point(s) to match the object points, got {}",
+                expected, actual
+            ),
+            PoseSolverError::NoSolution => write!(formatter, "No pose fits the image points"),
+            PoseSolverError::OpenCV(error) => write!(formatter, "OpenCV error: {}", error),
+            PoseSolverError::TooManyAssignments { assignments, limit } => write!(
+                formatter,
+                "{} candidate marker assignment(s) exceed the limit of {}",
+                assignments, limit
+            ),
+        }
+    }
+}
+
+impl Error for PoseSolverError {}
+
+impl From<OpenCVError> for PoseSolverError {
+    fn from(error: OpenCVError) -> PoseSolverError {
+        PoseSolverError::OpenCV(error)
+    }
+}
+
+impl Default for PoseSolver {
+    fn default() -> PoseSolver {
+        PoseSolver {
+            camera_intrinsics: Default::default(),
+            object_points: default_marker_constellation(),
+            pose_solver_config: Default::default(),
+            pose_convention: Default::default(),
+        }
+    }
+}
+
+impl PoseSolver {
+    pub fn new(
+        camera_intrinsics: CameraIntrinsics,
+        object_points: Vec<SafePoint3D>,
+        pose_solver_config: PoseSolverConfig,
+    ) -> Result<PoseSolver, PoseSolverError> {
+        camera_intrinsics
+            .validate()
+            .map_err(|error| PoseSolverError::InvalidCalibration(error.to_string()))?;
+
+        validate_marker_constellation(&object_points)
+            .and_then(|_| validate_pose_solver_config(&pose_solver_config, &object_points))
+            .map_err(|error| PoseSolverError::InvalidCalibration(error.to_string()))?;
+
+        if camera_intrinsics.distortion_model == DistortionModel::Fisheye
+            && pose_solver_config.undistortion == UndistortionMode::InSolver
+        {
+            return Err(PoseSolverError::InvalidCalibration(
+                "Fisheye distortion needs the PreStep undistortion mode".into(),
+            ));
+        }
+
+        Ok(PoseSolver {
+            camera_intrinsics,
+            object_points,
+            pose_solver_config,
+            pose_convention: Default::default(),
+        })
+    }
+
+    pub fn with_pose_convention(mut self, pose_convention: PoseConvention) -> PoseSolver {
+        self.pose_convention = pose_convention;
+        self
+    }
+
+    /// Uses the tracker's `CameraCalibrationPath` file for the intrinsics when it is set.
+    pub fn from_tracker_endpoint(tracker_endpoint: &TrackerEndpoint) -> Result<PoseSolver, PoseSolverError> {
+        let camera_intrinsics = match &tracker_endpoint.camera_calibration_path {
+            Some(camera_calibration_path) => {
+                CameraCalibration::load_config(camera_calibration_path)
+                    .map_err(|error| {
+                        PoseSolverError::InvalidCalibration(format!(
+                            "Cannot load \"{}\": {}",
+                            camera_calibration_path, error
+                        ))
+                    })?
+                    .camera_intrinsics
+            }
+            None => tracker_endpoint.camera_intrinsics.clone(),
+        };
+
+        PoseSolver::new(
+            camera_intrinsics,
+            tracker_endpoint.marker_constellation.clone(),
+            tracker_endpoint.pose_solver_config.clone(),
+        )
+    }
+
+    pub fn camera_intrinsics(&self) -> &CameraIntrinsics {
+        &self.camera_intrinsics
+    }
+
+    pub fn object_points(&self) -> &[SafePoint3D] {
+        &self.object_points
+    }
+
+    pub fn pose_solver_config(&self) -> &PoseSolverConfig {
+        &self.pose_solver_config
+    }
+
+    pub fn pose_convention(&self) -> &PoseConvention {
+        &self.pose_convention
+    }
 
-        for image_point_ref in image_points_array {
-            image_points.push(Point2d::new(image_point_ref.x, image_point_ref.y));
+    /// Turns raw pixel points into the image points the solver expects: undistorted under the `PreStep` undistortion
+    /// mode, unchanged under `InSolver`. Prepare a frame once and reuse it for every solve.
+    pub fn prepare_image_points(
+        &self,
+        image_points_array: &[SafePoint2D],
+    ) -> Result<Vec<SafePoint2D>, PoseSolverError> {
+        match self.pose_solver_config.undistortion {
+            UndistortionMode::InSolver => Ok(image_points_array.to_vec()),
+            UndistortionMode::PreStep => Ok(undistort_image_points(&self.camera_intrinsics, image_points_array)?),
         }
+    }
+
+    pub fn compute_pose(&self, image_points_array: &[SafePoint2D]) -> Result<PoseEstimate, PoseSolverError> {
+        let (extrinsics, inliers) = self.solve_extrinsics(image_points_array, None)?;
+        self.pose_estimate(&extrinsics, image_points_array, inliers)
+    }
+
+    /// Solves with the configured method, or refines `extrinsic_guess` with the iterative solver and no RANSAC.
+    /// Returns the extrinsics and the inlier indices.
+    pub fn solve_extrinsics(
+        &self,
+        image_points_array: &[SafePoint2D],
+        extrinsic_guess: Option<&Extrinsics>,
+    ) -> Result<(Extrinsics, Vec<usize>), PoseSolverError> {
+        self.check_image_point_count(image_points_array)?;
+
+        let object_points = to_vector_of_point3d(&self.object_points);
+        let image_points = to_vector_of_point2d(image_points_array);
+        let camera_matrix = mat_from_rows(&self.camera_intrinsics.camera_matrix())?;
+        let dist_coeffs = self.solver_distortion_coefficients()?;
+
+        let (is_solved, rotation_vector, translation_vector, inliers) =
+            match (extrinsic_guess, &self.pose_solver_config.ransac) {
+                (Some(extrinsic_guess), _) => {
+                    let mut rotation_vector = mat_from_column(&extrinsic_guess.rotation_vector)?;
+                    let mut translation_vector = mat_from_column(&extrinsic_guess.translation_vector)?;
+                    let is_solved = solve_pnp(
+                        &object_points,
+                        &image_points,
+                        &camera_matrix,
+                        &dist_coeffs,
+                        &mut rotation_vector,
+                        &mut translation_vector,
+                        true,
+                        SOLVEPNP_ITERATIVE,
+                    )?;
+
+                    (
+                        is_solved,
+                        rotation_vector,
+                        translation_vector,
+                        (0..image_points_array.len()).collect(),
+                    )
+                }
+                (None, Some(ransac_config)) => {
+                    let mut rotation_vector = Mat::default()?;
+                    let mut translation_vector = Mat::default()?;
+                    let mut inlier_indices = Mat::default()?;
+                    let is_solved = solve_pnp_ransac(
+                        &object_points,
+                        &image_points,
+                        &camera_matrix,
+                        &dist_coeffs,
+                        &mut rotation_vector,
+                        &mut translation_vector,
+                        false,
+                        ransac_config.iterations_count as i32,
+                        ransac_config.reprojection_error as f32,
+                        ransac_config.confidence,
+                        &mut inlier_indices,
+                        pnp_method_flags(self.pose_solver_config.pnp_method),
+                    )?;
+
+                    (
+                        is_solved,
+                        rotation_vector,
+                        translation_vector,
+                        read_inlier_indices(&inlier_indices)?,
+                    )
+                }
+                (None, None) => {
+                    let mut rotation_vector = Mat::default()?;
+                    let mut translation_vector = Mat::default()?;
+                    let is_solved = solve_pnp(
+                        &object_points,
+                        &image_points,
+                        &camera_matrix,
+                        &dist_coeffs,
+                        &mut rotation_vector,
+                        &mut translation_vector,
+                        false,
+                        pnp_method_flags(self.pose_solver_config.pnp_method),
+                    )?;
+
+                    (
+                        is_solved,
+                        rotation_vector,
+                        translation_vector,
+                        (0..image_points_array.len()).collect(),
+                    )
+                }
+            };
+
+        if !is_solved {
+            return Err(PoseSolverError::NoSolution);
+        }
+
+        let extrinsics = Extrinsics {
+            rotation_vector: column_to_array(&rotation_vector)?,
+            translation_vector: column_to_array(&translation_vector)?,
+        };
+
+        Ok((extrinsics, inliers))
+    }
 
-        let mut rotation_vector: Mat = Mat::default().unwrap();
-        let mut translation_vector: Mat = Mat::default().unwrap();
-        solve_pnp(
-            object_points_ref,
+    /// Solves the pose from exactly four of the markers with `AP3P`, a cheap hypothesis that ignores the configured
+    /// method and RANSAC. Returns `None` when no pose fits.
+    pub fn solve_minimal_extrinsics(
+        &self,
+        object_indices: &[usize],
+        image_points_array: &[SafePoint2D],
+    ) -> Result<Option<Extrinsics>, PoseSolverError> {
+        let object_points: Vec<SafePoint3D> = object_indices
+            .iter()
+            .map(|index| self.object_points[*index].clone())
+            .collect();
+
+        if object_points.len() != MIN_MARKER_CONSTELLATION_POINTS || image_points_array.len() != object_points.len() {
+            return Err(PoseSolverError::ImagePointCountMismatch {
+                expected: MIN_MARKER_CONSTELLATION_POINTS,
+                actual: image_points_array.len(),
+            });
+        }
+
+        let object_points = to_vector_of_point3d(&object_points);
+        let image_points = to_vector_of_point2d(image_points_array);
+        let camera_matrix = mat_from_rows(&self.camera_intrinsics.camera_matrix())?;
+        let dist_coeffs = self.solver_distortion_coefficients()?;
+        let mut rotation_vector = Mat::default()?;
+        let mut translation_vector = Mat::default()?;
+
+        if !solve_pnp(
+            &object_points,
             &image_points,
-            camera_matrix,
-            dist_coeffs,
+            &camera_matrix,
+            &dist_coeffs,
             &mut rotation_vector,
             &mut translation_vector,
             false,
-            0,
+            SOLVEPNP_AP3P,
+        )? {
+            return Ok(None);
+        }
+
+        Ok(Some(Extrinsics {
+            rotation_vector: column_to_array(&rotation_vector)?,
+            translation_vector: column_to_array(&translation_vector)?,
+        }))
+    }
+
+    /// Image positions of every marker under `extrinsics`, in the solver's undistortion mode. Unlike `pose_quality`
+    /// this skips the Jacobian, so it is cheap enough to score many hypotheses.
+    pub fn project_object_points(&self, extrinsics: &Extrinsics) -> Result<Vec<SafePoint2D>, PoseSolverError> {
+        let object_points = to_vector_of_point3d(&self.object_points);
+        let camera_matrix = mat_from_rows(&self.camera_intrinsics.camera_matrix())?;
+        let dist_coeffs = self.solver_distortion_coefficients()?;
+        let rotation_vector = mat_from_column(&extrinsics.rotation_vector)?;
+        let translation_vector = mat_from_column(&extrinsics.translation_vector)?;
+        let mut projected_points = VectorOfPoint2d::new();
+        project_points(
+            &object_points,
+            &rotation_vector,
+            &translation_vector,
+            &camera_matrix,
+            &dist_coeffs,
+            &mut projected_points,
+            &mut no_array()?,
+            0.0,
         )?;
-        let mut no_array = no_array().unwrap();
-        rodrigues(&rotation_vector, &mut translation_vector, &mut no_array)?;
-        let rv_val_0_2 = *rotation_vector.at_2d::<f64>(0, 2).unwrap();
-        let rv_val_1_0 = *rotation_vector.at_2d::<f64>(1, 0).unwrap();
-        let rv_val_1_1 = *rotation_vector.at_2d::<f64>(1, 1).unwrap();
-        let rv_val_1_2 = *rotation_vector.at_2d::<f64>(1, 2).unwrap();
-        let rv_val_2_2 = *rotation_vector.at_2d::<f64>(2, 2).unwrap();
-        let mut dst = Mat::new_rows_cols(3, 1, f64::typ()).unwrap();
-        *dst.at_2d_mut::<f64>(0, 0).unwrap() = rv_val_1_2.asin().to_degrees();
-        *dst.at_2d_mut::<f64>(1, 0).unwrap() = rv_val_0_2.atan2(rv_val_2_2).to_degrees();
-        *dst.at_2d_mut::<f64>(2, 0).unwrap() = (-rv_val_1_0.atan2(rv_val_1_1)).to_degrees();
-        translation[0] = translation_vector.at_3d::<f64>(2, 0, 0).unwrap().round() / 10.0;
-        translation[1] = translation_vector.at_3d::<f64>(0, 0, 0).unwrap().round() / 10.0;
-        translation[2] = translation_vector.at_3d::<f64>(1, 0, 0).unwrap().round() / 10.0;
-        euler_angles[0] = dst.at_2d::<f64>(2, 0).unwrap().round();
-        euler_angles[1] = dst.at_2d::<f64>(0, 0).unwrap().round();
-        euler_angles[2] = dst.at_2d::<f64>(1, 0).unwrap().round();
-
-        let _ = rotation_vector.release();
-        let _ = translation_vector.release();
-        let _ = no_array.release();
-        let _ = dst.release();
+
+        Ok(projected_points
+            .to_vec()
+            .iter()
+            .map(|point| SafePoint2D { x: point.x, y: point.y })
+            .collect())
+    }
+
+    /// Reprojection errors, conditioning and confidence of `extrinsics` against the image points. The condition
+    /// number comes from the inlier rows of the `project_points` Jacobian with respect to the six pose parameters.
+    pub fn pose_quality(
+        &self,
+        extrinsics: &Extrinsics,
+        image_points_array: &[SafePoint2D],
+        inliers: &[usize],
+    ) -> Result<PoseQuality, PoseSolverError> {
+        self.check_image_point_count(image_points_array)?;
+
+        let object_points = to_vector_of_point3d(&self.object_points);
+        let camera_matrix = mat_from_rows(&self.camera_intrinsics.camera_matrix())?;
+        let dist_coeffs = self.solver_distortion_coefficients()?;
+        let rotation_vector = mat_from_column(&extrinsics.rotation_vector)?;
+        let translation_vector = mat_from_column(&extrinsics.translation_vector)?;
+        let mut projected_points = VectorOfPoint2d::new();
+        let mut jacobian = Mat::default()?;
+        project_points(
+            &object_points,
+            &rotation_vector,
+            &translation_vector,
+            &camera_matrix,
+            &dist_coeffs,
+            &mut projected_points,
+            &mut jacobian,
+            0.0,
+        )?;
+
+        let mut reprojection_errors = Vec::with_capacity(image_points_array.len());
+
+        for (index, image_point) in image_points_array.iter().enumerate() {
+            let projected_point = projected_points.get(index)?;
+            reprojection_errors.push((projected_point.x - image_point.x).hypot(projected_point.y - image_point.y));
+        }
+
+        let inlier_reprojection_errors: Vec<f64> = inliers.iter().map(|index| reprojection_errors[*index]).collect();
+        let rms_reprojection_error = rms(&inlier_reprojection_errors);
+        let condition_number = pose_condition_number(&jacobian, inliers, constellation_scale(&self.object_points))?;
+        let is_degenerate = inliers.len() < MIN_MARKER_CONSTELLATION_POINTS
+            || !condition_number.is_finite()
+            || condition_number > DEGENERATE_CONDITION_NUMBER;
+        let confidence = if is_degenerate {
+            0.0
+        } else {
+            let inlier_ratio = inliers.len() as f64 / image_points_array.len() as f64;
+            let error_ratio = rms_reprojection_error / self.pose_solver_config.tracking_reprojection_threshold;
+            inlier_ratio / (1.0 + error_ratio * error_ratio)
+        };
+
+        Ok(PoseQuality {
+            reprojection_errors,
+            rms_reprojection_error,
+            condition_number,
+            is_degenerate,
+            confidence,
+        })
+    }
+
+    pub fn pose_estimate(
+        &self,
+        extrinsics: &Extrinsics,
+        image_points_array: &[SafePoint2D],
+        inliers: Vec<usize>,
+    ) -> Result<PoseEstimate, PoseSolverError> {
+        let quality = self.pose_quality(extrinsics, image_points_array, &inliers)?;
+        let translation = SafePoint3D::new(
+            extrinsics.translation_vector[0],
+            extrinsics.translation_vector[1],
+            extrinsics.translation_vector[2],
+        );
+        let pose = pose_from_camera_frame(
+            &rotation_matrix_from_rotation_vector(&extrinsics.rotation_vector),
+            &translation,
+            &self.pose_convention,
+        );
+
+        Ok(PoseEstimate { pose, inliers, quality })
+    }
+
+    /// The distortion coefficients handed to OpenCV, zero when the image points were undistorted beforehand.
+    fn solver_distortion_coefficients(&self) -> Result<Mat, OpenCVError> {
+        match self.pose_solver_config.undistortion {
+            UndistortionMode::InSolver => distortion_coefficients_mat(&self.camera_intrinsics),
+            UndistortionMode::PreStep => new_f64_mat(self.camera_intrinsics.distortion_coefficients.len(), 1),
+        }
+    }
+
+    fn check_image_point_count(&self, image_points_array: &[SafePoint2D]) -> Result<(), PoseSolverError> {
+        if image_points_array.len() != self.object_points.len() {
+            return Err(PoseSolverError::ImagePointCountMismatch {
+                expected: self.object_points.len(),
+                actual: image_points_array.len(),
+            });
+        }
 
         Ok(())
     }
 }
+
+/// RMS distance of the marker points from their centroid, in the constellation's length unit.
+fn constellation_scale(object_points: &[SafePoint3D]) -> f64 {
+    let center = centroid(object_points);
+    let distances: Vec<f64> = object_points.iter().map(|point| distance(point, &center)).collect();
+
+    rms(&distances)
+}
+
+/// Reads the rotation and translation columns of the inlier rows of the `project_points` Jacobian.
+fn pose_condition_number(jacobian: &Mat, inliers: &[usize], translation_scale: f64) -> Result<f64, OpenCVError> {
+    let mut jacobian_rows = Vec::with_capacity(inliers.len() * 2);
+
+    for inlier in inliers {
+        for row in (inlier * 2) as i32..(inlier * 2 + 2) as i32 {
+            let mut jacobian_row = [0.0; POSE_PARAMETER_COUNT];
+
+            for (col, value) in jacobian_row.iter_mut().enumerate() {
+                *value = *jacobian.at_2d::<f64>(row, col as i32)?;
+            }
+
+            jacobian_rows.push(jacobian_row);
+        }
+    }
+
+    Ok(condition_number(&jacobian_rows, translation_scale))
+}
+
+/// `sqrt(max / min)` eigenvalue of `JᵀJ`. Rotation columns are in px per radian; translation columns are multiplied
+/// by `translation_scale` so they are in px per constellation size rather than per length unit.
+fn condition_number(jacobian_rows: &[[f64; POSE_PARAMETER_COUNT]], translation_scale: f64) -> f64 {
+    let mut normal_matrix = vec![vec![0.0; POSE_PARAMETER_COUNT]; POSE_PARAMETER_COUNT];
+
+    for jacobian_row in jacobian_rows {
+        let mut scaled_row = *jacobian_row;
+
+        for value in scaled_row.iter_mut().skip(ROTATION_PARAMETER_COUNT) {
+            *value *= translation_scale;
+        }
+
+        for (left, normal_row) in normal_matrix.iter_mut().enumerate() {
+            for (right, value) in normal_row.iter_mut().enumerate() {
+                *value += scaled_row[left] * scaled_row[right];
+            }
+        }
+    }
+
+    // A diverged solve can leave NaN or infinity in the Jacobian, which no eigen decomposition can condition
+    if normal_matrix.iter().flatten().any(|value| !value.is_finite()) {
+        return f64::INFINITY;
+    }
+
+    let (eigenvalues, _) = symmetric_eigen(&normal_matrix);
+    let largest_eigenvalue = eigenvalues[0];
+    let smallest_eigenvalue = eigenvalues[POSE_PARAMETER_COUNT - 1];
+
+    if smallest_eigenvalue <= 0.0 {
+        return f64::INFINITY;
+    }
+
+    (largest_eigenvalue / smallest_eigenvalue).sqrt()
+}
+
+pub fn rms(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    (values.iter().map(|value| value * value).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+fn pnp_method_flags(pnp_method: PnPMethod) -> i32 {
+    match pnp_method {
+        PnPMethod::Iterative => SOLVEPNP_ITERATIVE,
+        PnPMethod::EPnP => SOLVEPNP_EPNP,
+        PnPMethod::P3P => SOLVEPNP_P3P,
+        PnPMethod::AP3P => SOLVEPNP_AP3P,
+        PnPMethod::IPPESquare => SOLVEPNP_IPPE_SQUARE,
+    }
+}
+
+fn read_inlier_indices(inlier_indices: &Mat) -> Result<Vec<usize>, OpenCVError> {
+    let inlier_count = inlier_indices.size()?.height;
+    let mut inliers = Vec::with_capacity(inlier_count as usize);
+
+    for row in 0..inlier_count {
+        inliers.push(*inlier_indices.at_2d::<i32>(row, 0)? as usize);
+    }
+
+    Ok(inliers)
+}
+
+fn column_to_array(column: &Mat) -> Result<[f64; 3], OpenCVError> {
+    Ok([
+        *column.at_2d::<f64>(0, 0)?,
+        *column.at_2d::<f64>(1, 0)?,
+        *column.at_2d::<f64>(2, 0)?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn pose_solver_is_shareable_and_rejects_bad_input() {
+        assert_send_sync::<PoseSolver>();
+
+        let object_points = default_marker_constellation();
+        assert!(PoseSolver::new(Default::default(), object_points[..3].to_vec(), Default::default()).is_err());
+
+        let camera_intrinsics = CameraIntrinsics {
+            fy: -1.0,
+            ..Default::default()
+        };
+        assert!(PoseSolver::new(camera_intrinsics, object_points.clone(), Default::default()).is_err());
+
+        let pose_solver_config = PoseSolverConfig {
+            pnp_method: PnPMethod::IPPESquare,
+            ..Default::default()
+        };
+        assert!(PoseSolver::new(Default::default(), object_points.clone(), pose_solver_config).is_err());
+
+        let camera_intrinsics = CameraIntrinsics {
+            distortion_model: DistortionModel::Fisheye,
+            ..Default::default()
+        };
+        assert!(PoseSolver::new(camera_intrinsics.clone(), object_points.clone(), Default::default()).is_err());
+        let pose_solver_config = PoseSolverConfig {
+            undistortion: UndistortionMode::PreStep,
+            ..Default::default()
+        };
+        assert!(PoseSolver::new(camera_intrinsics, object_points, pose_solver_config).is_ok());
+
+        let pose_solver = PoseSolver::default();
+        match pose_solver.compute_pose(&[SafePoint2D::default()]) {
+            Err(PoseSolverError::ImagePointCountMismatch { expected: 4, actual: 1 }) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn condition_number_ignores_the_length_unit() {
+        // Two points of a constellation 0.1 units wide seen from 1 unit away, with a slight rotation coupling
+        let jacobian_rows = [
+            [0.0, 1000.0, 50.0, 1000.0, 0.0, -50.0],
+            [-1000.0, 0.0, 40.0, 0.0, 1000.0, -40.0],
+            [0.0, 1010.0, -50.0, 1000.0, 0.0, 50.0],
+            [-990.0, 5.0, -40.0, 0.0, 1000.0, 40.0],
+            [20.0, 1000.0, 30.0, 1000.0, 0.0, -30.0],
+            [-1000.0, 10.0, 60.0, 0.0, 1000.0, 60.0],
+        ];
+        let metre_scale = 0.1;
+        let metre_condition_number = condition_number(&jacobian_rows, metre_scale);
+
+        // The same pose in millimetres: px per unit of translation shrink a thousandfold, the constellation grows
+        let millimetre_rows: Vec<[f64; POSE_PARAMETER_COUNT]> = jacobian_rows
+            .iter()
+            .map(|row| {
+                [
+                    row[0],
+                    row[1],
+                    row[2],
+                    row[3] / 1000.0,
+                    row[4] / 1000.0,
+                    row[5] / 1000.0,
+                ]
+            })
+            .collect();
+        let millimetre_condition_number = condition_number(&millimetre_rows, metre_scale * 1000.0);
+        assert!(metre_condition_number.is_finite());
+        assert!((metre_condition_number - millimetre_condition_number).abs() < 1e-6 * metre_condition_number);
+
+        let mut diverged_rows = jacobian_rows;
+        diverged_rows[2][4] = f64::NAN;
+        assert!(condition_number(&diverged_rows, metre_scale).is_infinite());
+
+        let object_points = default_marker_constellation();
+        assert!((constellation_scale(&object_points) - 11.4 * 2.0_f64.sqrt()).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::motion_tracker_compute::{Extrinsics, PoseSolver, PoseSolverError};
+use mcslib_common::types::{PoseEstimate, SafePoint2D};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrackingState {
+    /// Refined from the previous pose.
+    Tracked,
+    /// Solved from scratch, either on the first frame or after tracking was lost.
+    Reacquired,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackedPose {
+    pub pose_estimate: PoseEstimate,
+    pub tracking_state: TrackingState,
+}
+
+/// Per-tracker pose tracking for a continuous stream. Each frame is refined from the previous extrinsics and only
+/// solved from scratch when that refinement fails or its RMS reprojection error exceeds the solver's
+/// `TrackingReprojectionThreshold`.
+#[derive(Debug, Clone)]
+pub struct PoseTracker {
+    pose_solver: PoseSolver,
+    previous_extrinsics: Option<Extrinsics>,
+}
+
+impl PoseTracker {
+    pub fn new(pose_solver: PoseSolver) -> PoseTracker {
+        PoseTracker {
+            pose_solver,
+            previous_extrinsics: None,
+        }
+    }
+
+    pub fn pose_solver(&self) -> &PoseSolver {
+        &self.pose_solver
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        self.previous_extrinsics.is_some()
+    }
+
+    /// Forgets the previous pose so the next frame is solved from scratch.
+    pub fn reset(&mut self) {
+        self.previous_extrinsics = None;
+    }
+
+    pub fn track(&mut self, image_points: &[SafePoint2D]) -> Result<TrackedPose, PoseSolverError> {
+        if let Some(previous_extrinsics) = self.previous_extrinsics {
+            match self.refine(image_points, &previous_extrinsics) {
+                Ok(Some(tracked_pose)) => return Ok(tracked_pose),
+                Ok(None) => {}
+                Err(error @ PoseSolverError::ImagePointCountMismatch { .. }) => {
+                    self.reset();
+                    return Err(error);
+                }
+                Err(error) => debug!("Pose tracking failed, re-acquiring: {}", error),
+            }
+        }
+
+        self.reset();
+        let (extrinsics, inliers) = self.pose_solver.solve_extrinsics(image_points, None)?;
+        let pose_estimate = self.pose_solver.pose_estimate(&extrinsics, image_points, inliers)?;
+        self.previous_extrinsics = Some(extrinsics);
+
+        Ok(TrackedPose {
+            pose_estimate,
+            tracking_state: TrackingState::Reacquired,
+        })
+    }
+
+    /// Returns `None` when the refined pose no longer fits the image points.
+    fn refine(
+        &mut self,
+        image_points: &[SafePoint2D],
+        previous_extrinsics: &Extrinsics,
+    ) -> Result<Option<TrackedPose>, PoseSolverError> {
+        let (extrinsics, inliers) = self
+            .pose_solver
+            .solve_extrinsics(image_points, Some(previous_extrinsics))?;
+        let pose_estimate = self.pose_solver.pose_estimate(&extrinsics, image_points, inliers)?;
+        let rms_reprojection_error = pose_estimate.quality.rms_reprojection_error;
+        let tracking_reprojection_threshold = self.pose_solver.pose_solver_config().tracking_reprojection_threshold;
+
+        if rms_reprojection_error > tracking_reprojection_threshold {
+            debug!(
+                "Tracked pose reprojection error {:.3}px is above {:.3}px, re-acquiring",
+                rms_reprojection_error, tracking_reprojection_threshold
+            );
+            return Ok(None);
+        }
+
+        self.previous_extrinsics = Some(extrinsics);
+
+        Ok(Some(TrackedPose {
+            pose_estimate,
+            tracking_state: TrackingState::Tracked,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_pinhole;
+
+    fn project(pose_solver: &PoseSolver, extrinsics: &Extrinsics) -> Vec<SafePoint2D> {
+        project_pinhole(pose_solver.camera_intrinsics(), pose_solver.object_points(), extrinsics)
+    }
+
+    #[test]
+    fn pose_tracker_tracks_after_acquiring() {
+        let mut pose_tracker = PoseTracker::new(PoseSolver::default());
+        let extrinsics = Extrinsics {
+            rotation_vector: [-1.4, 0.1, 0.05],
+            translation_vector: [2.0, -3.0, 100.0],
+        };
+        let image_points = project(pose_tracker.pose_solver(), &extrinsics);
+
+        let tracked_pose = pose_tracker.track(&image_points).unwrap();
+        assert_eq!(tracked_pose.tracking_state, TrackingState::Reacquired);
+        assert!(pose_tracker.is_tracking());
+
+        let tracked_pose = pose_tracker.track(&image_points).unwrap();
+        assert_eq!(tracked_pose.tracking_state, TrackingState::Tracked);
+        assert!(tracked_pose.pose_estimate.quality.rms_reprojection_error < 1e-3);
+        assert!(!tracked_pose.pose_estimate.quality.is_degenerate);
+        assert!(tracked_pose.pose_estimate.quality.confidence > 0.99);
+        assert!((tracked_pose.pose_estimate.pose.position.z - 100.0).abs() < 1e-3);
+
+        assert!(pose_tracker.track(&image_points[..3]).is_err());
+        assert!(!pose_tracker.is_tracking());
+    }
+}
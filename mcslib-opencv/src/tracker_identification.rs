@@ -0,0 +1,429 @@
+use crate::correspondence::{solve_correspondence, Correspondence};
+use crate::motion_tracker_compute::{PoseSolver, PoseSolverError};
+use mcslib_common::types::{PoseEstimate, SafePoint2D, TrackerEndpoint};
+
+/// Image points further apart than this many times the median spanning tree edge of the frame belong to different
+/// clusters.
+pub const CLUSTER_GAP_RATIO: f64 = 3.0;
+
+/// A tracker found in the frame. `image_point_indices[i]` is the index in the frame of the point matched to marker
+/// `i` of its constellation.
+#[derive(Debug, Clone)]
+pub struct IdentifiedTracker {
+    pub tracker_name: String,
+    pub image_point_indices: Vec<usize>,
+    pub pose_estimate: PoseEstimate,
+    pub is_ambiguous: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameIdentification {
+    pub identified_trackers: Vec<IdentifiedTracker>,
+    pub unassigned_point_indices: Vec<usize>,
+}
+
+/// Splits the points one camera sees in a frame between several trackers, each known by its marker constellation,
+/// so a single camera stream can drive multiple `TrackerEndpoint`s.
+#[derive(Debug, Clone)]
+pub struct TrackerIdentifier {
+    pose_solvers: Vec<(String, PoseSolver)>,
+}
+
+impl TrackerIdentifier {
+    pub fn new(pose_solvers: Vec<(String, PoseSolver)>) -> TrackerIdentifier {
+        TrackerIdentifier { pose_solvers }
+    }
+
+    /// Trackers seen by the same camera, each with its own constellation and pose solver settings.
+    pub fn from_tracker_endpoints(tracker_endpoints: &[TrackerEndpoint]) -> Result<TrackerIdentifier, PoseSolverError> {
+        let mut pose_solvers = Vec::with_capacity(tracker_endpoints.len());
+
+        for tracker_endpoint in tracker_endpoints {
+            pose_solvers.push((
+                tracker_endpoint.tracker_name.clone(),
+                PoseSolver::from_tracker_endpoint(tracker_endpoint)?,
+            ));
+        }
+
+        Ok(TrackerIdentifier::new(pose_solvers))
+    }
+
+    pub fn tracker_names(&self) -> Vec<&str> {
+        self.pose_solvers
+            .iter()
+            .map(|(tracker_name, _)| tracker_name.as_str())
+            .collect()
+    }
+
+    /// Greedily claims points for trackers. The frame is first split into spatial clusters, and every tracker not yet
+    /// found solves its correspondence against the unassigned points of each cluster large enough for it. The best
+    /// fit within its solver's `TrackingReprojectionThreshold` takes its points, only the cluster it came from is
+    /// solved again, and the search repeats until no tracker fits. A near tracker with widely spaced markers may be
+    /// split over several clusters, so the trackers still missing are then solved once more against all the points
+    /// left, as a single cluster. Trackers with too few points left, or too many to try, are not reported. The image
+    /// points are raw pixels, prepared once for each tracker's solver.
+    pub fn identify(&self, image_points: &[SafePoint2D]) -> Result<FrameIdentification, PoseSolverError> {
+        let mut clusters = cluster_image_points(image_points);
+        let mut unidentified_trackers = Vec::with_capacity(self.pose_solvers.len());
+        let mut identified_trackers = vec![];
+
+        for (tracker_name, pose_solver) in &self.pose_solvers {
+            unidentified_trackers.push((
+                tracker_name,
+                pose_solver,
+                pose_solver.prepare_image_points(image_points)?,
+            ));
+        }
+
+        // solutions[tracker][cluster] is None until solved, and Some(None) when the tracker does not fit there
+        let mut solutions: Vec<Vec<Option<Option<Correspondence>>>> =
+            vec![vec![None; clusters.len()]; unidentified_trackers.len()];
+
+        loop {
+            let mut best: Option<(usize, usize, f64)> = None;
+
+            for (position, (tracker_name, pose_solver, prepared_points)) in unidentified_trackers.iter().enumerate() {
+                for (cluster_index, cluster) in clusters.iter().enumerate() {
+                    if solutions[position][cluster_index].is_none() {
+                        solutions[position][cluster_index] =
+                            Some(solve_in_cluster(tracker_name, pose_solver, prepared_points, cluster)?);
+                    }
+
+                    let correspondence = match &solutions[position][cluster_index] {
+                        Some(Some(correspondence)) => correspondence,
+                        _ => continue,
+                    };
+
+                    if best
+                        .map(|(_, _, best_rms_reprojection_error)| {
+                            correspondence.rms_reprojection_error < best_rms_reprojection_error
+                        })
+                        .unwrap_or(true)
+                    {
+                        best = Some((position, cluster_index, correspondence.rms_reprojection_error));
+                    }
+                }
+            }
+
+            let (position, cluster_index, _) = match best {
+                Some(best) => best,
+                None if clusters.len() > 1 && !unidentified_trackers.is_empty() => {
+                    let mut remaining_points: Vec<usize> = clusters.drain(..).flatten().collect();
+                    remaining_points.sort_unstable();
+                    clusters.push(remaining_points);
+                    solutions = vec![vec![None]; unidentified_trackers.len()];
+                    continue;
+                }
+                None => break,
+            };
+            let correspondence = match solutions.remove(position).swap_remove(cluster_index) {
+                Some(Some(correspondence)) => correspondence,
+                _ => unreachable!("The best tracker has a solution"),
+            };
+            let (tracker_name, _, _) = unidentified_trackers.remove(position);
+            let image_point_indices: Vec<usize> = correspondence
+                .assignment
+                .iter()
+                .map(|index| clusters[cluster_index][*index])
+                .collect();
+            clusters[cluster_index].retain(|index| !image_point_indices.contains(index));
+            solutions
+                .iter_mut()
+                .for_each(|tracker_solutions| tracker_solutions[cluster_index] = None);
+
+            identified_trackers.push(IdentifiedTracker {
+                tracker_name: tracker_name.clone(),
+                image_point_indices,
+                pose_estimate: correspondence.pose_estimate,
+                is_ambiguous: correspondence.is_ambiguous,
+            });
+        }
+
+        let mut unassigned_point_indices: Vec<usize> = clusters.into_iter().flatten().collect();
+        unassigned_point_indices.sort_unstable();
+
+        Ok(FrameIdentification {
+            identified_trackers,
+            unassigned_point_indices,
+        })
+    }
+}
+
+/// Correspondence of one tracker against the unassigned points of a cluster, with `cluster[i]` the frame index of
+/// its `i`th point. `None` when the tracker does not fit within its `TrackingReprojectionThreshold`.
+fn solve_in_cluster(
+    tracker_name: &str,
+    pose_solver: &PoseSolver,
+    prepared_points: &[SafePoint2D],
+    cluster: &[usize],
+) -> Result<Option<Correspondence>, PoseSolverError> {
+    if cluster.len() < pose_solver.object_points().len() {
+        return Ok(None);
+    }
+
+    let cluster_points: Vec<SafePoint2D> = cluster.iter().map(|index| prepared_points[*index].clone()).collect();
+    let correspondence = match solve_correspondence(pose_solver, &cluster_points) {
+        Ok(correspondence) => correspondence,
+        Err(PoseSolverError::OpenCV(error)) => return Err(PoseSolverError::OpenCV(error)),
+        Err(error) => {
+            debug!("Tracker \"{}\" not identified: {}", tracker_name, error);
+            return Ok(None);
+        }
+    };
+
+    if correspondence.rms_reprojection_error > pose_solver.pose_solver_config().tracking_reprojection_threshold {
+        return Ok(None);
+    }
+
+    Ok(Some(correspondence))
+}
+
+/// Splits image points into spatial clusters by single linkage: a minimum spanning tree over the points is cut at
+/// every edge longer than `CLUSTER_GAP_RATIO` times its median edge. Each cluster lists frame indices in ascending
+/// order, so trackers apart from each other and from stray blobs are solved separately.
+fn cluster_image_points(image_points: &[SafePoint2D]) -> Vec<Vec<usize>> {
+    let point_distance = |a: &SafePoint2D, b: &SafePoint2D| (a.x - b.x).hypot(a.y - b.y);
+    let point_count = image_points.len();
+
+    if point_count < 2 {
+        return vec![(0..point_count).collect()];
+    }
+
+    // Prim's algorithm from the first point, recording each point's tree edge in the order points join the tree
+    let mut is_in_tree = vec![false; point_count];
+    let mut nearest_tree_edge = vec![(0, f64::INFINITY); point_count];
+    let mut tree_edges = Vec::with_capacity(point_count - 1);
+    let mut next = 0;
+    is_in_tree[0] = true;
+
+    for _ in 1..point_count {
+        for index in 0..point_count {
+            let edge_length = point_distance(&image_points[next], &image_points[index]);
+
+            if !is_in_tree[index] && edge_length < nearest_tree_edge[index].1 {
+                nearest_tree_edge[index] = (next, edge_length);
+            }
+        }
+
+        next = (0..point_count)
+            .filter(|index| !is_in_tree[*index])
+            .min_by(|left, right| nearest_tree_edge[*left].1.total_cmp(&nearest_tree_edge[*right].1))
+            .unwrap_or_default();
+        is_in_tree[next] = true;
+        tree_edges.push((next, nearest_tree_edge[next].0, nearest_tree_edge[next].1));
+    }
+
+    let mut edge_lengths: Vec<f64> = tree_edges.iter().map(|(_, _, edge_length)| *edge_length).collect();
+    edge_lengths.sort_unstable_by(|left, right| left.total_cmp(right));
+    let gap = edge_lengths[edge_lengths.len() / 2] * CLUSTER_GAP_RATIO;
+    let mut labels = vec![0; point_count];
+    let mut cluster_count = 1;
+
+    // Parents join the tree before their children, so their label is already final
+    for (index, parent, edge_length) in tree_edges {
+        labels[index] = if edge_length > gap {
+            cluster_count += 1;
+            cluster_count - 1
+        } else {
+            labels[parent]
+        };
+    }
+
+    let mut clusters = vec![vec![]; cluster_count];
+    labels
+        .iter()
+        .enumerate()
+        .for_each(|(index, label)| clusters[*label].push(index));
+
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motion_tracker_compute::Extrinsics;
+    use crate::project_pinhole;
+    use mcslib_common::types::{default_marker_constellation, CameraIntrinsics, SafePoint3D};
+
+    #[test]
+    fn trackers_without_enough_points_are_not_identified() {
+        let tracker_endpoint = TrackerEndpoint {
+            tracker_name: "Head".into(),
+            ..Default::default()
+        };
+        let tracker_identifier = TrackerIdentifier::from_tracker_endpoints(&[tracker_endpoint]).unwrap();
+        assert_eq!(tracker_identifier.tracker_names(), vec!["Head"]);
+
+        let frame_identification = tracker_identifier.identify(&vec![SafePoint2D::default(); 3]).unwrap();
+        assert!(frame_identification.identified_trackers.is_empty());
+        assert_eq!(frame_identification.unassigned_point_indices, vec![0, 1, 2]);
+
+        let mut tracker_endpoint = TrackerEndpoint::default();
+        tracker_endpoint.marker_constellation.truncate(2);
+        assert!(TrackerIdentifier::from_tracker_endpoints(&[tracker_endpoint]).is_err());
+    }
+
+    #[test]
+    fn distant_point_groups_form_separate_clusters() {
+        let image_points: Vec<SafePoint2D> = [
+            (100.0, 100.0),
+            (500.0, 120.0),
+            (110.0, 100.0),
+            (510.0, 130.0),
+            (105.0, 112.0),
+            (900.0, 700.0),
+            (495.0, 131.0),
+        ]
+        .iter()
+        .map(|(x, y)| SafePoint2D { x: *x, y: *y })
+        .collect();
+
+        assert_eq!(
+            cluster_image_points(&image_points),
+            vec![vec![0, 2, 4], vec![1, 3, 6], vec![5]]
+        );
+        assert_eq!(cluster_image_points(&image_points[..1]), vec![vec![0]]);
+        assert_eq!(cluster_image_points(&[]), vec![Vec::<usize>::new()]);
+    }
+
+    #[test]
+    fn projected_constellations_are_told_apart() {
+        let camera_intrinsics = CameraIntrinsics {
+            fx: 800.0,
+            fy: 800.0,
+            cx: 640.0,
+            cy: 400.0,
+            ..Default::default()
+        };
+        let wand = vec![
+            SafePoint3D::new(0.0, 0.0, 0.0),
+            SafePoint3D::new(40.0, 0.0, 0.0),
+            SafePoint3D::new(0.0, 25.0, 0.0),
+            SafePoint3D::new(10.0, 10.0, 20.0),
+            SafePoint3D::new(-15.0, 30.0, 5.0),
+        ];
+        let head = vec![
+            SafePoint3D::new(0.0, 0.0, 0.0),
+            SafePoint3D::new(30.0, 0.0, 0.0),
+            SafePoint3D::new(0.0, 20.0, 10.0),
+            SafePoint3D::new(25.0, 35.0, 0.0),
+        ];
+        let wand_extrinsics = Extrinsics {
+            rotation_vector: [0.3, -0.2, 0.1],
+            translation_vector: [-150.0, -20.0, 400.0],
+        };
+        let head_extrinsics = Extrinsics {
+            rotation_vector: [-0.1, 0.4, -0.2],
+            translation_vector: [120.0, 30.0, 350.0],
+        };
+        let wand_points = project_pinhole(&camera_intrinsics, &wand, &wand_extrinsics);
+        let head_points = project_pinhole(&camera_intrinsics, &head, &head_extrinsics);
+        let image_points = vec![
+            head_points[2].clone(),
+            wand_points[4].clone(),
+            wand_points[0].clone(),
+            head_points[0].clone(),
+            SafePoint2D { x: 1200.0, y: 50.0 },
+            wand_points[3].clone(),
+            head_points[3].clone(),
+            wand_points[1].clone(),
+            head_points[1].clone(),
+            wand_points[2].clone(),
+        ];
+        let tracker_identifier = TrackerIdentifier::new(vec![
+            (
+                "Wand".into(),
+                PoseSolver::new(camera_intrinsics.clone(), wand, Default::default()).unwrap(),
+            ),
+            (
+                "Head".into(),
+                PoseSolver::new(camera_intrinsics, head, Default::default()).unwrap(),
+            ),
+        ]);
+
+        let frame_identification = tracker_identifier.identify(&image_points).unwrap();
+        let mut identified_trackers = frame_identification.identified_trackers;
+        identified_trackers.sort_by(|left, right| left.tracker_name.cmp(&right.tracker_name));
+        assert_eq!(identified_trackers.len(), 2);
+        assert_eq!(identified_trackers[0].tracker_name, "Head");
+        assert_eq!(identified_trackers[0].image_point_indices, vec![3, 8, 0, 6]);
+        assert_eq!(identified_trackers[1].tracker_name, "Wand");
+        assert_eq!(identified_trackers[1].image_point_indices, vec![2, 7, 9, 5, 1]);
+        assert_eq!(frame_identification.unassigned_point_indices, vec![4]);
+    }
+
+    #[test]
+    fn near_wide_tracker_split_over_clusters_is_identified() {
+        let camera_intrinsics = CameraIntrinsics {
+            fx: 800.0,
+            fy: 800.0,
+            cx: 640.0,
+            cy: 400.0,
+            ..Default::default()
+        };
+        let wand = vec![
+            SafePoint3D::new(0.0, 0.0, 0.0),
+            SafePoint3D::new(160.0, 0.0, 0.0),
+            SafePoint3D::new(0.0, 100.0, 0.0),
+            SafePoint3D::new(40.0, 40.0, 80.0),
+            SafePoint3D::new(-60.0, 120.0, 20.0),
+        ];
+        let wand_extrinsics = Extrinsics {
+            rotation_vector: [0.3, -0.2, 0.1],
+            translation_vector: [-60.0, -50.0, 350.0],
+        };
+        let mut image_points = project_pinhole(&camera_intrinsics, &wand, &wand_extrinsics);
+
+        for translation_vector in &[[-250.0, 150.0, 700.0], [300.0, 180.0, 750.0]] {
+            let square_extrinsics = Extrinsics {
+                rotation_vector: [1.2, 0.3, 0.0],
+                translation_vector: *translation_vector,
+            };
+            image_points.extend(project_pinhole(
+                &camera_intrinsics,
+                &default_marker_constellation(),
+                &square_extrinsics,
+            ));
+        }
+
+        // The small far squares set the gap, so the near wand falls apart into several clusters
+        let clusters = cluster_image_points(&image_points);
+        assert!(clusters
+            .iter()
+            .all(|cluster| !(0..5).all(|index| cluster.contains(&index))));
+
+        let tracker_identifier = TrackerIdentifier::new(vec![
+            (
+                "Left".into(),
+                PoseSolver::new(
+                    camera_intrinsics.clone(),
+                    default_marker_constellation(),
+                    Default::default(),
+                )
+                .unwrap(),
+            ),
+            (
+                "Wand".into(),
+                PoseSolver::new(camera_intrinsics.clone(), wand, Default::default()).unwrap(),
+            ),
+            (
+                "Right".into(),
+                PoseSolver::new(camera_intrinsics, default_marker_constellation(), Default::default()).unwrap(),
+            ),
+        ]);
+
+        let frame_identification = tracker_identifier.identify(&image_points).unwrap();
+        let mut identified_trackers = frame_identification.identified_trackers;
+        identified_trackers.sort_by(|left, right| left.tracker_name.cmp(&right.tracker_name));
+        assert_eq!(identified_trackers.len(), 3);
+        assert_eq!(identified_trackers[2].tracker_name, "Wand");
+        assert_eq!(identified_trackers[2].image_point_indices, vec![0, 1, 2, 3, 4]);
+        let mut square_point_indices: Vec<usize> = identified_trackers[..2]
+            .iter()
+            .flat_map(|identified_tracker| identified_tracker.image_point_indices.clone())
+            .collect();
+        square_point_indices.sort_unstable();
+        assert_eq!(square_point_indices, (5..13).collect::<Vec<usize>>());
+        assert!(frame_identification.unassigned_point_indices.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{mat_from_column, mat_from_rows, to_vector_of_point2d};
+use mcslib_common::types::{CameraIntrinsics, DistortionModel, SafePoint2D};
+use opencv::calib3d::fisheye_undistort_points;
+use opencv::core::Mat;
+use opencv::imgproc::undistort_points;
+use opencv::prelude::Vector;
+use opencv::types::VectorOfPoint2d;
+use opencv::Error as OpenCVError;
+
+const FISHEYE_COEFFICIENT_COUNT: usize = 4;
+
+fn has_distortion(camera_intrinsics: &CameraIntrinsics) -> bool {
+    let coefficients = match camera_intrinsics.distortion_model {
+        DistortionModel::RadialTangential => &camera_intrinsics.distortion_coefficients[..],
+        DistortionModel::Fisheye => &camera_intrinsics.distortion_coefficients[..FISHEYE_COEFFICIENT_COUNT],
+    };
+
+    coefficients.iter().any(|coefficient| *coefficient != 0.0)
+}
+
+/// Distortion coefficients of `camera_intrinsics` as OpenCV expects them for its distortion model.
+pub fn distortion_coefficients_mat(camera_intrinsics: &CameraIntrinsics) -> Result<Mat, OpenCVError> {
+    match camera_intrinsics.distortion_model {
+        DistortionModel::RadialTangential => mat_from_column(&camera_intrinsics.distortion_coefficients),
+        DistortionModel::Fisheye => {
+            mat_from_column(&camera_intrinsics.distortion_coefficients[..FISHEYE_COEFFICIENT_COUNT])
+        }
+    }
+}
+
+/// Removes lens distortion from pixel points. The result stays in pixels under the same camera matrix, so it can be
+/// solved with zero distortion coefficients by any algorithm.
+pub fn undistort_image_points(
+    camera_intrinsics: &CameraIntrinsics,
+    image_points: &[SafePoint2D],
+) -> Result<Vec<SafePoint2D>, OpenCVError> {
+    if image_points.is_empty() || !has_distortion(camera_intrinsics) {
+        return Ok(image_points.to_vec());
+    }
+
+    let distorted_points = to_vector_of_point2d(image_points);
+    let mut undistorted_points = VectorOfPoint2d::new();
+    let camera_matrix = mat_from_rows(&camera_intrinsics.camera_matrix())?;
+    let dist_coeffs = distortion_coefficients_mat(camera_intrinsics)?;
+    let rectification = Mat::default()?;
+
+    match camera_intrinsics.distortion_model {
+        DistortionModel::RadialTangential => undistort_points(
+            &distorted_points,
+            &mut undistorted_points,
+            &camera_matrix,
+            &dist_coeffs,
+            &rectification,
+            &camera_matrix,
+        )?,
+        DistortionModel::Fisheye => fisheye_undistort_points(
+            &distorted_points,
+            &mut undistorted_points,
+            &camera_matrix,
+            &dist_coeffs,
+            &rectification,
+            &camera_matrix,
+        )?,
+    }
+
+    Ok(undistorted_points
+        .to_vec()
+        .iter()
+        .map(|point| SafePoint2D { x: point.x, y: point.y })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_vector_of_point3d;
+    use mcslib_common::types::SafePoint3D;
+    use opencv::calib3d::{fisheye_project_points, project_points};
+    use opencv::core::no_array;
+
+    /// Ideal and distorted pixels of a grid of normalized image coordinates, seen straight on at unit depth.
+    fn distorted_grid(camera_intrinsics: &CameraIntrinsics) -> (Vec<SafePoint2D>, Vec<SafePoint2D>) {
+        let object_points: Vec<SafePoint3D> = (-2..=2)
+            .flat_map(|row| (-2..=2).map(move |column| SafePoint3D::new(column as f64 * 0.2, row as f64 * 0.15, 1.0)))
+            .collect();
+        let ideal_points = object_points
+            .iter()
+            .map(|point| SafePoint2D {
+                x: camera_intrinsics.fx * point.x + camera_intrinsics.cx,
+                y: camera_intrinsics.fy * point.y + camera_intrinsics.cy,
+            })
+            .collect();
+        let object_points = to_vector_of_point3d(&object_points);
+        let camera_matrix = mat_from_rows(&camera_intrinsics.camera_matrix()).unwrap();
+        let dist_coeffs = distortion_coefficients_mat(camera_intrinsics).unwrap();
+        let rotation_vector = mat_from_column(&[0.0; 3]).unwrap();
+        let translation_vector = mat_from_column(&[0.0; 3]).unwrap();
+        let mut distorted_points = VectorOfPoint2d::new();
+
+        match camera_intrinsics.distortion_model {
+            DistortionModel::RadialTangential => project_points(
+                &object_points,
+                &rotation_vector,
+                &translation_vector,
+                &camera_matrix,
+                &dist_coeffs,
+                &mut distorted_points,
+                &mut no_array().unwrap(),
+                0.0,
+            ),
+            DistortionModel::Fisheye => fisheye_project_points(
+                &object_points,
+                &mut distorted_points,
+                &rotation_vector,
+                &translation_vector,
+                &camera_matrix,
+                &dist_coeffs,
+                0.0,
+                &mut no_array().unwrap(),
+            ),
+        }
+        .unwrap();
+
+        let distorted_points = distorted_points
+            .to_vec()
+            .iter()
+            .map(|point| SafePoint2D { x: point.x, y: point.y })
+            .collect();
+
+        (ideal_points, distorted_points)
+    }
+
+    #[test]
+    fn points_without_distortion_pass_through() {
+        let image_points = vec![SafePoint2D { x: 1.5, y: 2.5 }, SafePoint2D { x: 7.0, y: 3.0 }];
+        // The fifth coefficient is not part of the fisheye model
+        let camera_intrinsics = CameraIntrinsics {
+            distortion_coefficients: [0.0, 0.0, 0.0, 0.0, 0.3],
+            distortion_model: DistortionModel::Fisheye,
+            ..Default::default()
+        };
+        assert!(!has_distortion(&camera_intrinsics));
+        assert!(has_distortion(&CameraIntrinsics {
+            distortion_model: DistortionModel::RadialTangential,
+            ..camera_intrinsics.clone()
+        }));
+
+        let undistorted_points = undistort_image_points(&camera_intrinsics, &image_points).unwrap();
+        assert_eq!(undistorted_points.len(), 2);
+        assert!((undistorted_points[1].x - 7.0).abs() < f64::EPSILON);
+        assert!(undistort_image_points(&Default::default(), &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn distorted_points_are_recovered() {
+        let radial_tangential = CameraIntrinsics {
+            fx: 800.0,
+            fy: 780.0,
+            cx: 640.0,
+            cy: 400.0,
+            distortion_coefficients: [-0.12, 0.05, 0.001, -0.0015, 0.0],
+            distortion_model: DistortionModel::RadialTangential,
+        };
+        let fisheye = CameraIntrinsics {
+            distortion_coefficients: [0.05, -0.01, 0.004, -0.001, 0.0],
+            distortion_model: DistortionModel::Fisheye,
+            ..radial_tangential.clone()
+        };
+
+        for camera_intrinsics in &[radial_tangential, fisheye] {
+            let (ideal_points, distorted_points) = distorted_grid(camera_intrinsics);
+            let undistorted_points = undistort_image_points(camera_intrinsics, &distorted_points).unwrap();
+            assert_eq!(undistorted_points.len(), ideal_points.len());
+
+            // The corners move by several pixels, so a match this close rules out a pass-through
+            let largest_shift = ideal_points
+                .iter()
+                .zip(distorted_points.iter())
+                .map(|(ideal, distorted)| (ideal.x - distorted.x).hypot(ideal.y - distorted.y))
+                .fold(0.0, f64::max);
+            assert!(
+                largest_shift > 1.0,
+                "{:?}: {}",
+                camera_intrinsics.distortion_model,
+                largest_shift
+            );
+
+            for (ideal, undistorted) in ideal_points.iter().zip(undistorted_points.iter()) {
+                let error = (ideal.x - undistorted.x).hypot(ideal.y - undistorted.y);
+                assert!(error < 0.01, "{:?}: {}", camera_intrinsics.distortion_model, error);
+            }
+        }
+    }
+}
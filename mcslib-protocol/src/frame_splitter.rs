@@ -0,0 +1,64 @@
+use crate::tracker_packet::{has_valid_checksum, peek_frame_length};
+use crate::{PROTOCOL_MAGIC, PROTOCOL_MAX_FRAME_LENGTH};
+use mcslib_common::bytes::{Buf, Bytes, BytesMut};
+
+/// Splits a byte stream (TCP, serial) into complete tracker frames.
+///
+/// Garbage between frames, partial frames left over from a dropped connection and frames failing the checksum are
+/// skipped by scanning forward to the next frame magic.
+#[derive(Debug)]
+pub struct FrameSplitter {
+    buffer: BytesMut,
+    discarded_bytes: usize,
+}
+
+impl FrameSplitter {
+    pub fn new() -> FrameSplitter {
+        FrameSplitter {
+            buffer: BytesMut::with_capacity(PROTOCOL_MAX_FRAME_LENGTH),
+            discarded_bytes: 0,
+        }
+    }
+
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn next_frame(&mut self) -> Option<Bytes> {
+        loop {
+            match peek_frame_length(&self.buffer) {
+                Ok(None) => return None,
+                Ok(Some(frame_length)) if self.buffer.len() < frame_length => return None,
+                Ok(Some(frame_length)) if has_valid_checksum(&self.buffer[..frame_length]) => {
+                    return Some(self.buffer.split_to(frame_length).freeze());
+                }
+                _ => self.resync(),
+            }
+        }
+    }
+
+    pub fn discarded_bytes(&self) -> usize {
+        self.discarded_bytes
+    }
+
+    pub fn clear(&mut self) {
+        self.discarded_bytes += self.buffer.len();
+        self.buffer.clear();
+    }
+
+    fn resync(&mut self) {
+        let skip_length = self.buffer[1..]
+            .windows(PROTOCOL_MAGIC.len())
+            .position(|window| window == PROTOCOL_MAGIC)
+            .map(|position| position + 1)
+            .unwrap_or_else(|| self.buffer.len().saturating_sub(PROTOCOL_MAGIC.len() - 1).max(1));
+        self.buffer.advance(skip_length);
+        self.discarded_bytes += skip_length;
+    }
+}
+
+impl Default for FrameSplitter {
+    fn default() -> FrameSplitter {
+        FrameSplitter::new()
+    }
+}
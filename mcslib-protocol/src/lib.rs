@@ -1,7 +1,149 @@
+//! Binary wire protocol spoken by the motion trackers.
+//!
+//! Every tracker packet is sent as a single self-delimiting frame. All multi-byte fields are big-endian.
+//!
+//! | Offset     | Size   | Field                                                  |
+//! |------------|--------|--------------------------------------------------------|
+//! | 0          | 4      | Magic, ASCII `MCSP`                                    |
+//! | 4          | 1      | Protocol version, currently `1`                        |
+//! | 5          | 1      | Reserved, always `0`                                   |
+//! | 6          | 2      | Tracker id (`u16`)                                     |
+//! | 8          | 4      | Sequence number (`u32`, wraps around)                  |
+//! | 12         | 8      | Capture timestamp, nanoseconds since UNIX epoch (`i64`)|
+//! | 20         | 2      | Image point count `N` (`u16`)                          |
+//! | 22         | 16 * N | Image points, each `X: f64` followed by `Y: f64`       |
+//! | 22 + 16 * N| 2      | CRC-16/CCITT-FALSE over every preceding byte           |
+//!
+//! The point count in the header is enough to know the full frame length, so the same frame can be carried as a
+//! UDP datagram or streamed back-to-back over TCP and serial links (see [`FrameSplitter`]).
+
+pub mod frame_splitter;
+pub mod tracker_packet;
+
+pub use frame_splitter::*;
+pub use tracker_packet::*;
+
+pub const PROTOCOL_MAGIC: [u8; 4] = *b"MCSP";
+pub const PROTOCOL_VERSION: u8 = 1;
+pub const PROTOCOL_HEADER_LENGTH: usize = 22;
+pub const PROTOCOL_IMAGE_POINT_LENGTH: usize = 16;
+pub const PROTOCOL_CHECKSUM_LENGTH: usize = 2;
+pub const PROTOCOL_MAX_IMAGE_POINTS: usize = 256;
+pub const PROTOCOL_MAX_FRAME_LENGTH: usize =
+    PROTOCOL_HEADER_LENGTH + PROTOCOL_MAX_IMAGE_POINTS * PROTOCOL_IMAGE_POINT_LENGTH + PROTOCOL_CHECKSUM_LENGTH;
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use mcslib_common::types::SafePoint2D;
+
+    fn sample_packet() -> TrackerPacket {
+        TrackerPacket::new(
+            TrackerPacketHeader::new(7, 42, 1_577_836_800_000_000_000),
+            vec![
+                SafePoint2D { x: 12.5, y: -3.25 },
+                SafePoint2D { x: 640.0, y: 480.0 },
+                SafePoint2D { x: 0.0, y: 1.0 },
+                SafePoint2D { x: 320.125, y: 240.5 },
+            ],
+        )
+    }
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let packet = sample_packet();
+        let frame = encode(&packet).unwrap();
+        assert_eq!(frame.len(), packet.frame_length());
+        assert_eq!(peek_frame_length(&frame).unwrap(), Some(frame.len()));
+
+        let decoded = decode(&frame).unwrap();
+        assert_eq!(decoded.header, packet.header);
+        assert_eq!(decoded.image_points.len(), packet.image_points.len());
+
+        for (decoded_point, point) in decoded.image_points.iter().zip(packet.image_points.iter()) {
+            assert_eq!(decoded_point.x, point.x);
+            assert_eq!(decoded_point.y, point.y);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_malformed_frames() {
+        let frame = encode(&sample_packet()).unwrap().to_vec();
+        assert_eq!(
+            decode(&frame[..10]).unwrap_err(),
+            ProtocolError::Truncated {
+                expected: PROTOCOL_HEADER_LENGTH,
+                actual: 10
+            }
+        );
+        assert_eq!(
+            decode(&frame[..frame.len() - 1]).unwrap_err(),
+            ProtocolError::Truncated {
+                expected: frame.len(),
+                actual: frame.len() - 1
+            }
+        );
+
+        let mut bad_magic = frame.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(decode(&bad_magic).unwrap_err(), ProtocolError::InvalidMagic(*b"XCSP"));
+
+        let mut bad_version = frame.clone();
+        bad_version[4] = PROTOCOL_VERSION + 1;
+        assert_eq!(
+            decode(&bad_version).unwrap_err(),
+            ProtocolError::UnsupportedVersion(PROTOCOL_VERSION + 1)
+        );
+
+        let mut corrupted = frame;
+        corrupted[PROTOCOL_HEADER_LENGTH] ^= 0xFF;
+        match decode(&corrupted).unwrap_err() {
+            ProtocolError::ChecksumMismatch { .. } => {}
+            error => panic!("Unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn frame_splitter_resyncs_on_garbage() {
+        let frame = encode(&sample_packet()).unwrap();
+        let mut corrupted = frame.to_vec();
+        corrupted[PROTOCOL_HEADER_LENGTH] ^= 0xFF;
+
+        let mut stream = b"garbage".to_vec();
+        stream.extend_from_slice(&frame);
+        stream.extend_from_slice(&corrupted);
+        stream.extend_from_slice(b"MC");
+        stream.extend_from_slice(&frame);
+
+        let mut frame_splitter = FrameSplitter::new();
+        let mut frames = Vec::new();
+
+        for chunk in stream.chunks(5) {
+            frame_splitter.extend(chunk);
+
+            while let Some(frame) = frame_splitter.next_frame() {
+                frames.push(frame);
+            }
+        }
+
+        assert_eq!(frames, vec![frame.clone(), frame.clone()]);
+        assert_eq!(frame_splitter.discarded_bytes(), 7 + frame.len() + 2);
+    }
+
+    #[test]
+    fn encode_rejects_too_many_points() {
+        let packet = TrackerPacket::new(
+            TrackerPacketHeader::new(1, 0, 0),
+            vec![Default::default(); PROTOCOL_MAX_IMAGE_POINTS + 1],
+        );
+        assert_eq!(
+            encode(&packet).unwrap_err(),
+            ProtocolError::TooManyImagePoints(PROTOCOL_MAX_IMAGE_POINTS + 1)
+        );
+    }
 }
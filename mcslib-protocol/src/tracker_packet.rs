@@ -0,0 +1,229 @@
+use crate::{
+    PROTOCOL_CHECKSUM_LENGTH, PROTOCOL_HEADER_LENGTH, PROTOCOL_IMAGE_POINT_LENGTH, PROTOCOL_MAGIC,
+    PROTOCOL_MAX_IMAGE_POINTS, PROTOCOL_VERSION,
+};
+use mcslib_common::bytes::{Buf, BufMut, Bytes, BytesMut};
+use mcslib_common::types::SafePoint2D;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FormatterResult};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    Truncated { expected: usize, actual: usize },
+    InvalidMagic([u8; 4]),
+    UnsupportedVersion(u8),
+    TooManyImagePoints(usize),
+    ChecksumMismatch { expected: u16, actual: u16 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackerPacketHeader {
+    pub version: u8,
+    pub tracker_id: u16,
+    pub sequence_number: u32,
+    pub timestamp_nanos: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct TrackerPacket {
+    pub header: TrackerPacketHeader,
+    pub image_points: Vec<SafePoint2D>,
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            ProtocolError::Truncated { expected, actual } => write!(
+                formatter,
+                "Truncated frame, expected {} bytes but got {}",
+                expected, actual
+            ),
+            ProtocolError::InvalidMagic(magic) => write!(formatter, "Invalid frame magic {:02X?}", magic),
+            ProtocolError::UnsupportedVersion(version) => {
+                write!(formatter, "Unsupported protocol version {}", version)
+            }
+            ProtocolError::TooManyImagePoints(count) => write!(
+                formatter,
+                "Too many image points ({}), maximum is {}",
+                count, PROTOCOL_MAX_IMAGE_POINTS
+            ),
+            ProtocolError::ChecksumMismatch { expected, actual } => write!(
+                formatter,
+                "Checksum mismatch, expected {:#06X} but got {:#06X}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
+
+impl TrackerPacketHeader {
+    pub fn new(tracker_id: u16, sequence_number: u32, timestamp_nanos: i64) -> TrackerPacketHeader {
+        TrackerPacketHeader {
+            version: PROTOCOL_VERSION,
+            tracker_id,
+            sequence_number,
+            timestamp_nanos,
+        }
+    }
+}
+
+impl TrackerPacket {
+    pub fn new(header: TrackerPacketHeader, image_points: Vec<SafePoint2D>) -> TrackerPacket {
+        TrackerPacket { header, image_points }
+    }
+
+    pub fn frame_length(&self) -> usize {
+        get_frame_length(self.image_points.len())
+    }
+}
+
+#[inline]
+fn get_frame_length(image_point_count: usize) -> usize {
+    PROTOCOL_HEADER_LENGTH + image_point_count * PROTOCOL_IMAGE_POINT_LENGTH + PROTOCOL_CHECKSUM_LENGTH
+}
+
+/// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF, no reflection, no final XOR).
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+#[inline]
+fn get_checksums(frame: &[u8]) -> (u16, u16) {
+    let checksum_offset = frame.len() - PROTOCOL_CHECKSUM_LENGTH;
+    let expected_checksum = (&frame[checksum_offset..]).get_u16();
+    let actual_checksum = crc16(&frame[..checksum_offset]);
+
+    (expected_checksum, actual_checksum)
+}
+
+pub(crate) fn has_valid_checksum(frame: &[u8]) -> bool {
+    let (expected_checksum, actual_checksum) = get_checksums(frame);
+    expected_checksum == actual_checksum
+}
+
+/// Returns the total length of the frame starting at `buffer[0]`, or `None` when not enough bytes have arrived yet
+/// to read the header. Stream receivers use this to split a byte stream into frames.
+pub fn peek_frame_length(buffer: &[u8]) -> Result<Option<usize>, ProtocolError> {
+    if buffer.len() < PROTOCOL_HEADER_LENGTH {
+        return Ok(None);
+    }
+
+    let mut reader = buffer;
+    let mut magic = [0u8; 4];
+    reader.copy_to_slice(&mut magic);
+
+    if magic != PROTOCOL_MAGIC {
+        return Err(ProtocolError::InvalidMagic(magic));
+    }
+
+    let version = reader.get_u8();
+
+    if version != PROTOCOL_VERSION {
+        return Err(ProtocolError::UnsupportedVersion(version));
+    }
+
+    let image_point_count = (&buffer[PROTOCOL_HEADER_LENGTH - 2..]).get_u16() as usize;
+
+    if image_point_count > PROTOCOL_MAX_IMAGE_POINTS {
+        return Err(ProtocolError::TooManyImagePoints(image_point_count));
+    }
+
+    Ok(Some(get_frame_length(image_point_count)))
+}
+
+pub fn encode(packet: &TrackerPacket) -> Result<Bytes, ProtocolError> {
+    let image_point_count = packet.image_points.len();
+
+    if image_point_count > PROTOCOL_MAX_IMAGE_POINTS {
+        return Err(ProtocolError::TooManyImagePoints(image_point_count));
+    }
+
+    let mut frame = BytesMut::with_capacity(packet.frame_length());
+    frame.put_slice(&PROTOCOL_MAGIC);
+    frame.put_u8(packet.header.version);
+    frame.put_u8(0);
+    frame.put_u16(packet.header.tracker_id);
+    frame.put_u32(packet.header.sequence_number);
+    frame.put_i64(packet.header.timestamp_nanos);
+    frame.put_u16(image_point_count as u16);
+
+    for image_point in &packet.image_points {
+        frame.put_f64(image_point.x);
+        frame.put_f64(image_point.y);
+    }
+
+    let checksum = crc16(&frame);
+    frame.put_u16(checksum);
+
+    Ok(frame.freeze())
+}
+
+pub fn decode(frame: &[u8]) -> Result<TrackerPacket, ProtocolError> {
+    let frame_length = match peek_frame_length(frame)? {
+        Some(frame_length) => frame_length,
+        None => {
+            return Err(ProtocolError::Truncated {
+                expected: PROTOCOL_HEADER_LENGTH,
+                actual: frame.len(),
+            })
+        }
+    };
+
+    if frame.len() < frame_length {
+        return Err(ProtocolError::Truncated {
+            expected: frame_length,
+            actual: frame.len(),
+        });
+    }
+
+    let checksum_offset = frame_length - PROTOCOL_CHECKSUM_LENGTH;
+    let (expected_checksum, actual_checksum) = get_checksums(&frame[..frame_length]);
+
+    if expected_checksum != actual_checksum {
+        return Err(ProtocolError::ChecksumMismatch {
+            expected: expected_checksum,
+            actual: actual_checksum,
+        });
+    }
+
+    let mut reader = &frame[PROTOCOL_MAGIC.len()..checksum_offset];
+    let version = reader.get_u8();
+    let _reserved = reader.get_u8();
+    let tracker_id = reader.get_u16();
+    let sequence_number = reader.get_u32();
+    let timestamp_nanos = reader.get_i64();
+    let image_point_count = reader.get_u16() as usize;
+    let mut image_points = Vec::with_capacity(image_point_count);
+
+    for _ in 0..image_point_count {
+        let x = reader.get_f64();
+        let y = reader.get_f64();
+        image_points.push(SafePoint2D { x, y });
+    }
+
+    Ok(TrackerPacket {
+        header: TrackerPacketHeader {
+            version,
+            tracker_id,
+            sequence_number,
+            timestamp_nanos,
+        },
+        image_points,
+    })
+}